@@ -3,7 +3,7 @@ use gregex::*;
 
 fn main() {
     let runner = regex!(dot!('a', 'b', 'c'));
-    assert_eq!(runner.run("abc"), true);
-    assert_eq!(runner.run("ab"), false);
-    assert_eq!(runner.run("abcd"), false);
+    assert!(runner.run("abc"));
+    assert!(!runner.run("ab"));
+    assert!(!runner.run("abcd"));
 }
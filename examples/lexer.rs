@@ -0,0 +1,57 @@
+extern crate gregex;
+use gregex_logic::lexer::{LexError, Lexer};
+use gregex_logic::nfa::NFA;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Let,
+    Identifier,
+    Number,
+    Operator,
+    Parenthesis,
+    Whitespace,
+}
+
+/// Splits the source into tokens, skipping whitespace.
+fn tokenize(source: &str) -> Result<Vec<(Token, &str)>, LexError> {
+    let rule = |pattern: &str, token| (NFA::new(pattern).expect("Invalid rule"), token);
+    // `let` comes before identifiers, so it wins when both match the same text
+    let lexer = Lexer::new(vec![
+        rule("let", Token::Let),
+        rule("[a-zA-Z_][a-zA-Z_0-9]*", Token::Identifier),
+        rule("[0-9][0-9]*", Token::Number),
+        rule("[+*/=-]", Token::Operator),
+        rule(r"\(|\)", Token::Parenthesis),
+        rule("[ \t\n][ \t\n]*", Token::Whitespace),
+    ]);
+    let tokens = lexer.tokenize(source)?;
+    Ok(tokens
+        .into_iter()
+        .filter(|(token, _)| *token != Token::Whitespace)
+        .collect())
+}
+
+fn main() {
+    let source = "let total = (price + 42) * letters";
+    let tokens = tokenize(source).unwrap();
+    for (token, text) in &tokens {
+        println!("{:<12} {:?}", format!("{:?}", token), text);
+    }
+
+    assert_eq!(
+        tokens,
+        vec![
+            (Token::Let, "let"),
+            (Token::Identifier, "total"),
+            (Token::Operator, "="),
+            (Token::Parenthesis, "("),
+            (Token::Identifier, "price"),
+            (Token::Operator, "+"),
+            (Token::Number, "42"),
+            (Token::Parenthesis, ")"),
+            (Token::Operator, "*"),
+            (Token::Identifier, "letters"),
+        ]
+    );
+    assert_eq!(tokenize("a # b"), Err(LexError::NoRuleMatches { at: 2 }));
+}
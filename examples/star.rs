@@ -3,7 +3,7 @@ use gregex::*;
 
 fn main() {
     let runner = regex!(star!('a'));
-    assert_eq!(runner.run("a"), true);
-    assert_eq!(runner.run("aa"), true);
-    assert_eq!(runner.run(""), true);
+    assert!(runner.run("a"));
+    assert!(runner.run("aa"));
+    assert!(runner.run(""));
 }
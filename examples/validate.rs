@@ -0,0 +1,75 @@
+extern crate gregex;
+use gregex_logic::nfa::NFA;
+use gregex_logic::parser::ParseError;
+
+/// Returns the byte offset in the pattern where the error was found.
+fn position(error: &ParseError) -> usize {
+    match *error {
+        ParseError::UnbalancedParenthesis { at }
+        | ParseError::DanglingOperator { at, .. }
+        | ParseError::TrailingEscape { at }
+        | ParseError::UnbalancedClass { at }
+        | ParseError::InvalidRange { at }
+        | ParseError::NegatedClass { at }
+        | ParseError::InvalidRepetition { at }
+        | ParseError::RepetitionTooLarge { at }
+        | ParseError::UnknownFlag { at, .. }
+        | ParseError::NestingTooDeep { at }
+        | ParseError::ClassTooLarge { at } => at,
+    }
+}
+
+/// Compiles the pattern, describing the size of its NFA, or the error under the pattern with a
+/// caret pointing at it.
+fn validate(pattern: &str) -> Result<String, String> {
+    match NFA::new(pattern) {
+        Ok(nfa) => {
+            let stats = nfa.stats();
+            Ok(format!(
+                "{:?}: {} states, {} transitions, {} accepting{}",
+                pattern,
+                stats.states,
+                stats.transitions,
+                stats.accept_states,
+                if stats.nullable { ", nullable" } else { "" }
+            ))
+        }
+        Err(error) => {
+            // The caret is placed by characters, the offset being in bytes
+            let column = pattern[..position(&error)].chars().count();
+            Err(format!("{}\n{}^ {}", pattern, " ".repeat(column), error))
+        }
+    }
+}
+
+fn main() {
+    let patterns: Vec<String> = std::env::args().skip(1).collect();
+    if !patterns.is_empty() {
+        for pattern in &patterns {
+            match validate(pattern) {
+                Ok(summary) => println!("{}", summary),
+                Err(report) => println!("{}", report),
+            }
+        }
+        return;
+    }
+
+    // Without arguments, check a few patterns instead
+    assert_eq!(
+        validate("(a|b)*c"),
+        Ok(r#""(a|b)*c": 4 states, 9 transitions, 1 accepting"#.to_string())
+    );
+    assert_eq!(
+        validate("a**|()"),
+        Ok(r#""a**|()": 2 states, 2 transitions, 2 accepting, nullable"#.to_string())
+    );
+    assert_eq!(
+        validate("ab)c"),
+        Err("ab)c\n  ^ unbalanced parenthesis at byte 2".to_string())
+    );
+    assert_eq!(
+        validate("é[z-a]"),
+        Err("é[z-a]\n  ^ the range at byte 3 ends before it starts".to_string())
+    );
+    println!("Run with patterns as arguments to validate them, like `cargo run --example validate -- 'a(b'`");
+}
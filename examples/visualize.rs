@@ -0,0 +1,34 @@
+extern crate gregex;
+use gregex::*;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn main() {
+    let runner = regex!(dot!('a', star!(or!('b', 'c'))));
+    let dot = runner.to_dot();
+
+    // Render with Graphviz if it is installed, otherwise print the DOT source
+    let graphviz = Command::new("dot")
+        .args(["-Tsvg", "-o", "nfa.svg"])
+        .stdin(Stdio::piped())
+        .spawn();
+    match graphviz {
+        Ok(mut child) => {
+            child
+                .stdin
+                .take()
+                .expect("Failed to open the stdin of dot")
+                .write_all(dot.as_bytes())
+                .expect("Failed to write to dot");
+            let status = child.wait().expect("Failed to wait for dot");
+            assert!(status.success(), "dot exited with {}", status);
+            println!("Wrote nfa.svg");
+        }
+        Err(_) => {
+            println!(
+                "Graphviz is not installed, here is the DOT source:\n{}",
+                dot
+            );
+        }
+    }
+}
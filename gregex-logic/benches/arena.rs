@@ -0,0 +1,54 @@
+//! Compares the time taken to compute the sets of a large pattern stored as a `Box` based [Node]
+//! tree and as a [NodeArena].
+//!
+//! Run with `cargo bench -p gregex-logic --bench arena`.
+
+use gregex_logic::translation::arena::NodeArena;
+use gregex_logic::translation::node::{factors_set, nullability_set, prefix_set, suffix_set, Node};
+use gregex_logic::translation::operator::Operator;
+use std::time::Instant;
+
+/// Builds `(a|b)*c(a|b)*c...` with `pieces` pieces, nested to the left.
+fn pattern(pieces: u32) -> Node {
+    let piece = || {
+        Node::Operation(
+            Operator::Concat,
+            Box::new(Node::Operation(
+                Operator::Production,
+                Box::new(Node::Operation(
+                    Operator::Or,
+                    Box::new(Node::Terminal('a', 0)),
+                    Some(Box::new(Node::Terminal('b', 0))),
+                )),
+                None,
+            )),
+            Some(Box::new(Node::Terminal('c', 0))),
+        )
+    };
+    let mut tree = (1..pieces).fold(piece(), |left, _| {
+        Node::Operation(Operator::Concat, Box::new(left), Some(Box::new(piece())))
+    });
+    tree.renumber();
+    tree
+}
+
+fn bench(name: &str, f: impl FnOnce() -> usize) {
+    let start = Instant::now();
+    let len = f();
+    println!("{:<24} {:>8} factors {:>10.2?}", name, len, start.elapsed());
+}
+
+fn main() {
+    let tree = pattern(20_000);
+    let arena = NodeArena::from_node(&tree);
+    bench("box", || {
+        nullability_set(&tree);
+        prefix_set(&tree);
+        suffix_set(&tree);
+        factors_set(&tree).len()
+    });
+    bench("arena", || arena.sets().factors.len());
+    bench("arena (with copy)", || {
+        NodeArena::from_node(&tree).sets().factors.len()
+    });
+}
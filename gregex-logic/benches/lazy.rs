@@ -0,0 +1,52 @@
+//! Compares the time taken to match a long input with the NFA, with a DFA built upfront and with
+//! a [LazyDFA], for caches large and small.
+//!
+//! Run with `cargo bench -p gregex-logic --bench lazy`.
+
+use gregex_logic::dfa::DFA;
+use gregex_logic::lazy::LazyDFA;
+use gregex_logic::translation::node::Node;
+use gregex_logic::translation::operator::Operator;
+use std::time::Instant;
+
+/// Builds `(a|b)*a(a|b)(a|b)...` with `tail` trailing alternations.
+fn pattern(tail: u32) -> Node {
+    let a_or_b = || {
+        Node::Operation(
+            Operator::Or,
+            Box::new(Node::Terminal('a', 0)),
+            Some(Box::new(Node::Terminal('b', 0))),
+        )
+    };
+    let head = Node::Operation(
+        Operator::Concat,
+        Box::new(Node::Operation(
+            Operator::Production,
+            Box::new(a_or_b()),
+            None,
+        )),
+        Some(Box::new(Node::Terminal('a', 0))),
+    );
+    let mut tree = (0..tail).fold(head, |left, _| {
+        Node::Operation(Operator::Concat, Box::new(left), Some(Box::new(a_or_b())))
+    });
+    tree.renumber();
+    tree
+}
+
+fn bench(name: &str, f: impl FnOnce() -> bool) {
+    let start = Instant::now();
+    let matched = f();
+    println!("{:<20} {:<5} {:>10.2?}", name, matched, start.elapsed());
+}
+
+fn main() {
+    let nfa = gregex_logic::regex(&pattern(10));
+    let input = "abbabaabbbaba".repeat(20_000);
+    bench("nfa", || nfa.run(&input));
+    bench("dfa (with build)", || DFA::from_nfa(&nfa).run(&input));
+    bench("lazy dfa", || LazyDFA::new(&nfa, 4096).run(&input));
+    bench("lazy dfa (16 states)", || {
+        LazyDFA::new(&nfa, 16).run(&input)
+    });
+}
@@ -0,0 +1,41 @@
+//! Compares the time taken by [NFA::find], which runs the NFA from every offset, and the single
+//! pass [NFA::leftmost_find] on a long haystack matching only at its end.
+//!
+//! Run with `cargo bench -p gregex-logic --bench search`.
+
+use gregex_logic::nfa::NFA;
+use gregex_logic::translation::node::Node;
+use gregex_logic::translation::operator::Operator;
+use std::time::Instant;
+
+/// Builds `(a|b)*c`.
+fn pattern() -> NFA {
+    gregex_logic::regex(&Node::Operation(
+        Operator::Concat,
+        Box::new(Node::Operation(
+            Operator::Production,
+            Box::new(Node::Operation(
+                Operator::Or,
+                Box::new(Node::Terminal('a', 1)),
+                Some(Box::new(Node::Terminal('b', 2))),
+            )),
+            None,
+        )),
+        Some(Box::new(Node::Terminal('c', 3))),
+    ))
+}
+
+fn bench(name: &str, f: impl Fn() -> Option<usize>) {
+    let start = Instant::now();
+    let found = f();
+    println!("{:<16} {:?} {:>10.2?}", name, found, start.elapsed());
+}
+
+fn main() {
+    let nfa = pattern();
+    let haystack = "ab".repeat(2_000) + "xc";
+    bench("find", || nfa.find(&haystack).map(|m| m.start()));
+    bench("leftmost_find", || {
+        nfa.leftmost_find(&haystack).map(|m| m.start())
+    });
+}
@@ -0,0 +1,78 @@
+//! Measures the time and the number of allocations taken by the set functions on a large pattern.
+//!
+//! Run with `cargo bench -p gregex-logic --bench sets`.
+
+use gregex_logic::translation::node::{factors_set, nullability_set, prefix_set, suffix_set, Node};
+use gregex_logic::translation::operator::Operator;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Builds `((x₁|y₁)*(x₂|y₂)*...)*` over `width` starred alternations of `depth` letters each.
+fn large_pattern(width: u32, depth: u32) -> Node {
+    let mut code = 0;
+    let mut terminal = |symbol: char| {
+        code += 1;
+        Node::Terminal(symbol, code)
+    };
+    let mut starred_alternations = (0..width).map(|_| {
+        let first = terminal('a');
+        let alternation = (1..depth).fold(first, |left, i| {
+            let symbol = char::from(b'a' + (i % 26) as u8);
+            Node::Operation(
+                Operator::Or,
+                Box::new(left),
+                Some(Box::new(terminal(symbol))),
+            )
+        });
+        Node::Operation(Operator::Production, Box::new(alternation), None)
+    });
+    let first = starred_alternations.next().unwrap();
+    let concatenation = starred_alternations.fold(first, |left, right| {
+        Node::Operation(Operator::Concat, Box::new(left), Some(Box::new(right)))
+    });
+    Node::Operation(Operator::Production, Box::new(concatenation), None)
+}
+
+fn bench(name: &str, tree: &Node, f: fn(&Node) -> usize) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let len = f(tree);
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    println!(
+        "{:<16} {:>8} elements {:>10.2?} {:>8} allocations",
+        name, len, elapsed, allocations
+    );
+}
+
+fn main() {
+    let tree = large_pattern(40, 20);
+    bench("nullability_set", &tree, |tree| nullability_set(tree).len());
+    bench("prefix_set", &tree, |tree| prefix_set(tree).len());
+    bench("suffix_set", &tree, |tree| suffix_set(tree).len());
+    bench("factors_set", &tree, |tree| factors_set(tree).len());
+}
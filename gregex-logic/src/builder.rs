@@ -0,0 +1,398 @@
+//! Contains the `RegexBuilder` struct, which compiles a regular expression tree to a NFA with configurable checks.
+
+use crate::nfa::NFA;
+use crate::parser::{parse_with_limit, ParseError, MAX_REPETITION_NODES};
+use crate::translation::node::{construction_cost, is_nullable, Node};
+use crate::translation::operator::Operator;
+use std::fmt;
+
+/// The `CompileError` enum represents the reasons a [RegexBuilder] can refuse a regular expression tree.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CompileError {
+    /// A star, plus or question mark is applied to an expression that already matches the empty
+    /// string, like `(a*)*` or `(a?)+`.
+    NullableStar,
+    /// An alternation has more branches than allowed by [RegexBuilder::max_alternatives].
+    TooManyAlternatives,
+    /// Building the NFA would take more set operations than allowed by [RegexBuilder::fuel].
+    OutOfFuel,
+    /// An operation of the tree has the wrong number of operands for its operator.
+    MalformedOperation(Operator),
+    /// The sets given to [NFA::try_set_to_nfa] hold an element of the wrong kind.
+    MalformedSets,
+    /// The counted repetitions of the pattern given to [RegexBuilder::build_pattern] would expand
+    /// to more nodes than allowed by [RegexBuilder::max_repetition_nodes].
+    RepetitionTooLarge,
+    /// The pattern given to [RegexBuilder::build_pattern] cannot be parsed.
+    Parse(ParseError),
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::NullableStar => {
+                write!(
+                    f,
+                    "a star is applied to an expression matching the empty string"
+                )
+            }
+            CompileError::TooManyAlternatives => {
+                write!(f, "an alternation has too many branches")
+            }
+            CompileError::OutOfFuel => write!(f, "the pattern is too costly to compile"),
+            CompileError::MalformedOperation(op) => {
+                write!(f, "an operation {:?} has the wrong number of operands", op)
+            }
+            CompileError::MalformedSets => write!(f, "the sets hold elements of the wrong kind"),
+            CompileError::RepetitionTooLarge => write!(f, "a repetition is too large"),
+            CompileError::Parse(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+/// The `NullableStars` enum tells a [RegexBuilder] what to do with stars, plus and question marks
+/// over nullable expressions.
+///
+/// Such operators, like `(a*)*` or `(a?)+`, match nothing more than their operand starred once or
+/// left as is, and loop on the empty string in constructions using epsilon transitions.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum NullableStars {
+    /// Compile the tree as is.
+    #[default]
+    Allow,
+    /// Fail with [CompileError::NullableStar].
+    Reject,
+    /// Rewrite directly nested operators: `(e*)*` becomes `e*`, `(e?)?` becomes `e?`, and mixed
+    /// ones like `(e?)+` or `(e+)?` become `e*`. Other nullable operands are kept as is.
+    Collapse,
+}
+
+/// The `RegexBuilder` struct compiles a regular expression tree to a [NFA], like [crate::regex], after
+/// applying the configured checks.
+#[derive(Debug, Default)]
+pub struct RegexBuilder {
+    nullable_stars: NullableStars,
+    max_input_len: Option<usize>,
+    max_alternatives: Option<usize>,
+    fuel: Option<u64>,
+    merge_equivalent_states: bool,
+    max_repetition_nodes: Option<usize>,
+}
+
+impl RegexBuilder {
+    /// Creates a builder with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how stars over nullable expressions are handled.
+    pub fn nullable_stars(mut self, nullable_stars: NullableStars) -> Self {
+        self.nullable_stars = nullable_stars;
+        self
+    }
+
+    /// Sets the maximum length in bytes of the inputs the NFA runs on. Longer inputs are rejected
+    /// by [NFA::run] without being simulated, which protects services from huge inputs.
+    pub fn max_input_len(mut self, max_input_len: usize) -> Self {
+        self.max_input_len = Some(max_input_len);
+        self
+    }
+
+    /// Sets the maximum number of branches of an alternation, nested alternations like `(a|b)|c`
+    /// counting as one. Wider alternations fail with [CompileError::TooManyAlternatives], which
+    /// bounds the compile time of machine-generated patterns.
+    pub fn max_alternatives(mut self, max_alternatives: usize) -> Self {
+        self.max_alternatives = Some(max_alternatives);
+        self
+    }
+
+    /// Sets the maximum number of set operations the construction may do. Patterns crafted to be
+    /// costly to compile fail with [CompileError::OutOfFuel] before any set is computed.
+    ///
+    /// The cost is an upper bound counting every node, and the size of the product of suffixes
+    /// and prefixes in concatenations and stars. A pattern of `n` terminals costs at most about `n * n`.
+    pub fn fuel(mut self, fuel: u64) -> Self {
+        self.fuel = Some(fuel);
+        self
+    }
+
+    /// Sets whether the equivalent states of the NFA are merged after the construction, see
+    /// [NFA::merge_equivalent_states].
+    pub fn merge_equivalent_states(mut self, merge: bool) -> Self {
+        self.merge_equivalent_states = merge;
+        self
+    }
+
+    /// Sets the maximum number of nodes the counted repetitions of a pattern given to
+    /// [RegexBuilder::build_pattern] can expand to in total, [MAX_REPETITION_NODES] by default.
+    /// Larger repetitions, like `a{1,1000000}`, fail with [CompileError::RepetitionTooLarge] before being expanded.
+    pub fn max_repetition_nodes(mut self, max_repetition_nodes: usize) -> Self {
+        self.max_repetition_nodes = Some(max_repetition_nodes);
+        self
+    }
+
+    /// Parses the pattern string and compiles its tree to a NFA like [RegexBuilder::build], see
+    /// [crate::parser] for the syntax.
+    pub fn build_pattern(&self, pattern: &str) -> Result<NFA, CompileError> {
+        let max_repetition_nodes = self.max_repetition_nodes.unwrap_or(MAX_REPETITION_NODES);
+        let tree =
+            parse_with_limit(pattern, max_repetition_nodes).map_err(|error| match error {
+                ParseError::RepetitionTooLarge { .. } => CompileError::RepetitionTooLarge,
+                error => CompileError::Parse(error),
+            })?;
+        self.build(tree)
+    }
+
+    /// Compiles the tree to a NFA. The terminals of the tree are renumbered, see [Node::renumber].
+    pub fn build(&self, mut regex_tree: Node) -> Result<NFA, CompileError> {
+        crate::check_operands(&regex_tree)?;
+        match self.nullable_stars {
+            NullableStars::Allow => {}
+            NullableStars::Reject => {
+                if has_nullable_star(&regex_tree) {
+                    return Err(CompileError::NullableStar);
+                }
+            }
+            NullableStars::Collapse => collapse_nested_stars(&mut regex_tree),
+        }
+        if self
+            .max_alternatives
+            .is_some_and(|max| widest_alternation(&regex_tree) > max)
+        {
+            return Err(CompileError::TooManyAlternatives);
+        }
+        if self
+            .fuel
+            .is_some_and(|fuel| construction_cost(&regex_tree) > fuel)
+        {
+            return Err(CompileError::OutOfFuel);
+        }
+        regex_tree.renumber();
+        let mut nfa = crate::try_compile(&regex_tree)?;
+        if self.merge_equivalent_states {
+            nfa = nfa.merge_equivalent_states();
+        }
+        nfa.max_input_len = self.max_input_len;
+        Ok(nfa)
+    }
+}
+
+/// Returns true if the tree has a star, plus or question mark over an expression matching the empty string.
+fn has_nullable_star(regex_tree: &Node) -> bool {
+    let mut pending = vec![regex_tree];
+    while let Some(node) = pending.pop() {
+        if let Node::Operation(op, left, right) = node {
+            if !op.is_binary() && is_nullable(left) {
+                return true;
+            }
+            pending.push(left);
+            pending.extend(right.as_deref());
+        }
+    }
+    false
+}
+
+/// Returns the number of branches of the widest alternation of the tree, `1` if it has none.
+fn widest_alternation(regex_tree: &Node) -> usize {
+    let mut widest = 1;
+    let mut pending = vec![regex_tree];
+    while let Some(node) = pending.pop() {
+        let Node::Operation(op, left, right) = node else {
+            continue;
+        };
+        if *op != Operator::Or {
+            pending.push(left);
+            pending.extend(right.as_deref());
+            continue;
+        }
+        // Flatten the nested alternations, their other operands being searched later
+        let mut width = 0;
+        let mut branches = vec![node];
+        while let Some(branch) = branches.pop() {
+            match branch {
+                Node::Operation(Operator::Or, left, Some(right)) => {
+                    branches.push(left);
+                    branches.push(right);
+                }
+                _ => {
+                    width += 1;
+                    pending.push(branch);
+                }
+            }
+        }
+        widest = widest.max(width);
+    }
+    widest
+}
+
+/// Rewrites every star, plus or question mark directly over another one, see [NullableStars::Collapse].
+///
+/// A chain of these operators collapses into its innermost one if they are all the same, and into
+/// a star otherwise.
+fn collapse_nested_stars(regex_tree: &mut Node) {
+    let mut pending = vec![regex_tree];
+    while let Some(node) = pending.pop() {
+        while let Node::Operation(outer, left, None) = node {
+            let Node::Operation(inner, _, None) = **left else {
+                break;
+            };
+            let same = *outer == inner;
+            *node = std::mem::replace(&mut **left, Node::Epsilon);
+            if let Node::Operation(op, _, _) = node {
+                if !same {
+                    *op = Operator::Production;
+                }
+            }
+        }
+        if let Node::Operation(_, left, right) = node {
+            pending.push(left);
+            pending.extend(right.as_deref_mut());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn star(node: Node) -> Node {
+        Node::Operation(Operator::Production, Box::new(node), None)
+    }
+
+    #[test]
+    fn nullable_stars_reject_test() {
+        let builder = RegexBuilder::new().nullable_stars(NullableStars::Reject);
+        assert_eq!(
+            builder
+                .build(star(star(Node::Terminal('a', 1))))
+                .unwrap_err(),
+            CompileError::NullableStar
+        );
+        assert!(builder.build(star(Node::Terminal('a', 1))).is_ok());
+        for pattern in ["(a*)+", "(a?)*", "(a|b*)?", "c(a*b?)+"] {
+            assert_eq!(
+                builder
+                    .build(crate::parser::parse(pattern).unwrap())
+                    .unwrap_err(),
+                CompileError::NullableStar,
+                "{:?}",
+                pattern
+            );
+        }
+        assert!(builder
+            .build(crate::parser::parse("(a+)+|b?").unwrap())
+            .is_ok());
+    }
+
+    #[test]
+    fn max_input_len_test() {
+        let nfa = RegexBuilder::new()
+            .max_input_len(4)
+            .build(star(Node::Terminal('a', 1)))
+            .unwrap();
+        assert!(nfa.run("aaaa"));
+        assert!(!nfa.run("aaaaa"));
+        assert!(!nfa.run("aab"));
+    }
+
+    #[test]
+    fn max_alternatives_test() {
+        let builder = RegexBuilder::new().max_alternatives(3);
+        let alternation = |symbols: &str| {
+            symbols
+                .chars()
+                .map(|c| Node::Terminal(c, 0))
+                .reduce(|left, right| {
+                    Node::Operation(Operator::Or, Box::new(left), Some(Box::new(right)))
+                })
+                .unwrap()
+        };
+        assert!(builder.build(alternation("abc")).is_ok());
+        assert_eq!(
+            builder.build(alternation("abcd")).unwrap_err(),
+            CompileError::TooManyAlternatives
+        );
+        // Two alternations of two branches, concatenated
+        let tree = Node::Operation(
+            Operator::Concat,
+            Box::new(alternation("ab")),
+            Some(Box::new(star(alternation("cd")))),
+        );
+        assert_eq!(widest_alternation(&tree), 2);
+        assert!(builder.build(tree).is_ok());
+    }
+
+    #[test]
+    fn fuel_test() {
+        let builder = RegexBuilder::new().fuel(100_000);
+        assert_eq!(
+            builder.build(star(Node::repeat('a', 1000))).unwrap_err(),
+            CompileError::OutOfFuel
+        );
+        let nfa = builder.build(star(Node::repeat('a', 100))).unwrap();
+        assert!(nfa.run("a".repeat(200)));
+    }
+
+    #[test]
+    fn merge_equivalent_states_test() {
+        let tree = crate::parser::parse("(abc|abc|abd)*").unwrap();
+        let nfa = RegexBuilder::new().build(tree.clone()).unwrap();
+        let merged = RegexBuilder::new()
+            .merge_equivalent_states(true)
+            .build(tree)
+            .unwrap();
+        assert!(merged.stats().states < nfa.stats().states);
+        for input in ["", "abc", "abdabc", "ab", "abcab", "abe"] {
+            assert_eq!(merged.run(input), nfa.run(input), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn max_repetition_nodes_test() {
+        let builder = RegexBuilder::new().max_repetition_nodes(1000);
+        assert_eq!(
+            builder.build_pattern("a{1,1000000}").unwrap_err(),
+            CompileError::RepetitionTooLarge
+        );
+        assert_eq!(
+            builder.build_pattern("(ab){300}").unwrap_err(),
+            CompileError::RepetitionTooLarge
+        );
+        let nfa = builder.build_pattern("(ab){2,50}").unwrap();
+        assert!(nfa.run("ab".repeat(50)));
+        assert!(!nfa.run("ab"));
+        assert_eq!(
+            builder.build_pattern("a{2").unwrap_err(),
+            CompileError::Parse(ParseError::InvalidRepetition { at: 1 })
+        );
+    }
+
+    #[test]
+    fn nullable_stars_collapse_test() {
+        let mut tree = star(star(star(Node::Terminal('a', 1))));
+        collapse_nested_stars(&mut tree);
+        assert_eq!(tree, star(Node::Terminal('a', 1)));
+
+        let parsed = |pattern: &str| {
+            let mut tree = crate::parser::parse(pattern).unwrap();
+            collapse_nested_stars(&mut tree);
+            tree.linearized()
+        };
+        assert_eq!(parsed("(a*)+"), "a1*");
+        assert_eq!(parsed("(a+)?"), "a1*");
+        assert_eq!(parsed("(a?)*"), "a1*");
+        assert_eq!(parsed("((a+)+)+b?"), "a1+b2?");
+        assert_eq!(parsed("(a?)?"), "a1?");
+        assert_eq!(parsed("(a*b?)+"), "(a1*b2?)+");
+
+        let nfa = RegexBuilder::new()
+            .nullable_stars(NullableStars::Collapse)
+            .build(star(star(Node::Terminal('a', 1))))
+            .unwrap();
+        assert!(nfa.run(""));
+        assert!(nfa.run("aaa"));
+        assert!(!nfa.run("b"));
+    }
+}
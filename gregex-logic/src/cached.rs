@@ -0,0 +1,183 @@
+//! Contains the `CachedRegex` struct, which remembers the results of a [NFA] for recent inputs.
+
+use crate::nfa::NFA;
+use std::collections::{BTreeMap, HashMap};
+
+#[cfg(not(feature = "sync"))]
+type Shared<T> = std::cell::RefCell<T>;
+#[cfg(feature = "sync")]
+type Shared<T> = std::sync::Mutex<T>;
+
+/// The `CachedRegex` struct runs a [NFA] like [NFA::run], caching the result of the last
+/// `capacity` distinct inputs so inputs seen again are answered without simulating the NFA.
+///
+/// The least recently used input is evicted when the cache is full. With the `sync` feature the
+/// cache is behind a mutex, so a `CachedRegex` can be shared between threads.
+#[derive(Debug)]
+pub struct CachedRegex {
+    nfa: NFA,
+    cache: Shared<Lru>,
+}
+
+/// The `Lru` struct holds the cached results, with the time each input was last used.
+#[derive(Debug)]
+struct Lru {
+    capacity: usize,
+    /// Map from an input to its result and the time it was last used.
+    results: HashMap<String, (bool, u64)>,
+    /// Map from the time an input was last used to the input, the oldest first.
+    recency: BTreeMap<u64, String>,
+    time: u64,
+    hits: u64,
+}
+
+impl Lru {
+    /// Returns the cached result of the input, marking it as the most recently used.
+    fn get(&mut self, input: &str) -> Option<bool> {
+        self.time += 1;
+        let (result, used) = self.results.get_mut(input)?;
+        let key = self
+            .recency
+            .remove(used)
+            .expect("every cached input has its time");
+        *used = self.time;
+        self.recency.insert(self.time, key);
+        self.hits += 1;
+        Some(*result)
+    }
+
+    /// Caches the result of the input, evicting the least recently used input if the cache is full.
+    ///
+    /// An input already cached, by another thread having missed at the same time, is replaced
+    /// rather than evicting another input.
+    fn insert(&mut self, input: &str, result: bool) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.time += 1;
+        if let Some((_, used)) = self.results.get(input) {
+            self.recency.remove(used);
+        } else if self.results.len() == self.capacity {
+            if let Some((_, oldest)) = self.recency.pop_first() {
+                self.results.remove(&oldest);
+            }
+        }
+        self.results.insert(input.to_string(), (result, self.time));
+        self.recency.insert(self.time, input.to_string());
+    }
+}
+
+impl CachedRegex {
+    /// Wraps the NFA with a cache of the results of at most `capacity` inputs.
+    pub fn new(nfa: NFA, capacity: usize) -> Self {
+        Self {
+            nfa,
+            cache: Shared::new(Lru {
+                capacity,
+                results: HashMap::new(),
+                recency: BTreeMap::new(),
+                time: 0,
+                hits: 0,
+            }),
+        }
+    }
+
+    /// Returns the wrapped NFA.
+    pub fn nfa(&self) -> &NFA {
+        &self.nfa
+    }
+
+    /// Simulates the NFA with the given input, or returns the cached result if the input was seen recently.
+    pub fn run(&self, input: impl AsRef<str>) -> bool {
+        let input = input.as_ref();
+        if let Some(result) = self.with_cache(|cache| cache.get(input)) {
+            return result;
+        }
+        let result = self.nfa.run(input);
+        self.with_cache(|cache| cache.insert(input, result));
+        result
+    }
+
+    /// Returns the number of inputs whose result is cached.
+    pub fn len(&self) -> usize {
+        self.with_cache(|cache| cache.results.len())
+    }
+
+    /// Returns true if no result is cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns how many times [CachedRegex::run] was answered from the cache.
+    pub fn hits(&self) -> u64 {
+        self.with_cache(|cache| cache.hits)
+    }
+
+    #[cfg(not(feature = "sync"))]
+    fn with_cache<T>(&self, f: impl FnOnce(&mut Lru) -> T) -> T {
+        f(&mut self.cache.borrow_mut())
+    }
+
+    #[cfg(feature = "sync")]
+    fn with_cache<T>(&self, f: impl FnOnce(&mut Lru) -> T) -> T {
+        // A panic while the lock is held cannot leave the cache inconsistent enough to matter
+        f(&mut self.cache.lock().unwrap_or_else(|err| err.into_inner()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cached_regex_test() {
+        let nfa = NFA::new("(ab)*c").unwrap();
+        let cached = CachedRegex::new(NFA::new("(ab)*c").unwrap(), 2);
+        let inputs = ["abc", "c", "abc", "ab", "abc", "c", "b"];
+        for input in inputs {
+            assert_eq!(cached.run(input), nfa.run(input), "{:?}", input);
+        }
+        // "abc" is hit twice, and "c" was evicted by "ab"
+        assert_eq!(cached.hits(), 2);
+        assert_eq!(cached.len(), 2);
+        assert_eq!(cached.run("b"), nfa.run("b"));
+        assert_eq!(cached.hits(), 3);
+
+        let uncached = CachedRegex::new(nfa, 0);
+        assert!(uncached.run("c"));
+        assert!(uncached.run("c"));
+        assert_eq!(uncached.hits(), 0);
+        assert!(uncached.is_empty());
+    }
+
+    #[test]
+    fn insert_twice_test() {
+        let cached = CachedRegex::new(NFA::new("a*").unwrap(), 2);
+        assert!(cached.run("a"));
+        assert!(!cached.run("b"));
+        // Two threads missing the same input both insert it
+        cached.with_cache(|cache| cache.insert("a", true));
+        cached.with_cache(|cache| {
+            assert_eq!(cache.results.len(), 2);
+            assert_eq!(cache.recency.len(), 2);
+        });
+        // "b" is now the least recently used, and "a" is kept
+        assert!(cached.run("aa"));
+        assert!(cached.run("a"));
+        assert_eq!(cached.hits(), 1);
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn shared_between_threads_test() {
+        let cached = CachedRegex::new(NFA::new("a*").unwrap(), 8);
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| assert!(cached.run("aaa")));
+            }
+        });
+        // Threads missing at the same time all run the NFA, but the input is cached once
+        assert!(cached.hits() <= 3);
+        assert_eq!(cached.len(), 1);
+    }
+}
@@ -0,0 +1,509 @@
+//! Has the implementation of a deterministic finite automaton (DFA), built from a [NFA] by subset construction.
+
+use crate::nfa::NFA;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+/// The `DFA` struct represents a deterministic finite automaton. State `0` is the start state.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct DFA {
+    /// Number of states, the states being `0..state_count`.
+    state_count: u32,
+    /// Set of all accepting states.
+    accept: HashSet<u32>,
+    /// The transition function is a map from a pair of a state and a character to the next state.
+    transition_function: HashMap<(u32, char), u32>,
+}
+
+/// The `DfaTable` struct is a flat, language-agnostic representation of a [DFA].
+///
+/// The transition of state `s` on `alphabet[i]` is `transitions[s * alphabet.len() + i]`,
+/// `None` meaning the input is rejected.
+#[derive(Debug, PartialEq, Eq)]
+pub struct DfaTable {
+    /// Number of states, the start state being `0`.
+    pub state_count: u32,
+    /// Sorted list of the characters having at least one transition.
+    pub alphabet: Vec<char>,
+    /// Row-major transition array of `state_count * alphabet.len()` entries.
+    pub transitions: Vec<Option<u32>>,
+    /// `accept[s]` is true if state `s` is accepting.
+    pub accept: Vec<bool>,
+}
+
+impl DFA {
+    /// Converts a NFA to a DFA by subset construction.
+    pub fn from_nfa(nfa: &NFA) -> Self {
+        let mut alphabet: Vec<char> = nfa.transition_function.keys().map(|&(_, c)| c).collect();
+        alphabet.sort_unstable();
+        alphabet.dedup();
+
+        let mut dfa = Self::default();
+        let mut ids: HashMap<BTreeSet<u32>, u32> = HashMap::new();
+        let mut pending = vec![BTreeSet::from([0])];
+        ids.insert(pending[0].clone(), 0);
+        dfa.state_count = 1;
+
+        while let Some(subset) = pending.pop() {
+            let id = ids[&subset];
+            if subset.iter().any(|state| nfa.accept.contains(state)) {
+                dfa.accept.insert(id);
+            }
+            for &c in &alphabet {
+                let next: BTreeSet<u32> = subset
+                    .iter()
+                    .filter_map(|&state| nfa.transition_function.get(&(state, c)))
+                    .flatten()
+                    .copied()
+                    .collect();
+                if next.is_empty() {
+                    continue;
+                }
+                let next_id = *ids.entry(next.clone()).or_insert_with(|| {
+                    pending.push(next);
+                    dfa.state_count += 1;
+                    dfa.state_count - 1
+                });
+                dfa.transition_function.insert((id, c), next_id);
+            }
+        }
+
+        dfa
+    }
+
+    /// Simulates the DFA with the given input.
+    pub fn run(&self, input: impl AsRef<str>) -> bool {
+        let input = input.as_ref();
+        let mut state = 0;
+        for c in input.chars() {
+            match self.transition_function.get(&(state, c)) {
+                Some(&next) => state = next,
+                None => return false,
+            }
+        }
+        self.accept.contains(&state)
+    }
+
+    /// Returns the sorted list of characters having at least one transition.
+    pub fn alphabet(&self) -> Vec<char> {
+        let mut alphabet: Vec<char> = self.transition_function.keys().map(|&(_, c)| c).collect();
+        alphabet.sort_unstable();
+        alphabet.dedup();
+        alphabet
+    }
+
+    /// Returns the partition of the states into Myhill–Nerode equivalence classes: two states are
+    /// in the same class if they accept the same suffixes, so minimizing the DFA merges each class
+    /// into one state. The classes are sorted by their smallest state.
+    ///
+    /// The partition is refined from accepting and rejecting states until it is stable. A missing
+    /// transition goes to an implicit dead state, equivalent to the states from which no accepting
+    /// state is reachable.
+    pub fn equivalence_classes(&self) -> Vec<HashSet<u32>> {
+        let alphabet = self.alphabet();
+
+        // States from which an accepting state is reachable
+        let mut live: HashSet<u32> = self.accept.clone();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (&(from, _), to) in &self.transition_function {
+                if live.contains(to) && live.insert(from) {
+                    changed = true;
+                }
+            }
+        }
+
+        let mut class: Vec<usize> = (0..self.state_count)
+            .map(|state| usize::from(self.accept.contains(&state)))
+            .collect();
+        let mut class_count = 0;
+        loop {
+            let mut ids: HashMap<(usize, Vec<Option<usize>>), usize> = HashMap::new();
+            let refined: Vec<usize> = (0..self.state_count)
+                .map(|state| {
+                    let targets = alphabet
+                        .iter()
+                        .map(|&c| {
+                            self.transition_function
+                                .get(&(state, c))
+                                .filter(|next| live.contains(next))
+                                .map(|&next| class[next as usize])
+                        })
+                        .collect();
+                    let next_id = ids.len();
+                    *ids.entry((class[state as usize], targets))
+                        .or_insert(next_id)
+                })
+                .collect();
+            class = refined;
+            if ids.len() == class_count {
+                break;
+            }
+            class_count = ids.len();
+        }
+
+        let mut classes: Vec<HashSet<u32>> = vec![HashSet::new(); class_count];
+        for (state, &id) in (0..self.state_count).zip(&class) {
+            classes[id].insert(state);
+        }
+        classes
+    }
+
+    /// Returns the DFA accepting exactly the strings over `alphabet` that this DFA rejects.
+    pub fn complement(&self, alphabet: &[char]) -> Self {
+        let dead = self.state_count;
+        let mut dfa = Self {
+            state_count: self.state_count + 1,
+            ..Self::default()
+        };
+        for state in 0..dfa.state_count {
+            if !self.accept.contains(&state) {
+                dfa.accept.insert(state);
+            }
+            for &c in alphabet {
+                let next = self
+                    .transition_function
+                    .get(&(state, c))
+                    .copied()
+                    .unwrap_or(dead);
+                dfa.transition_function.insert((state, c), next);
+            }
+        }
+        dfa
+    }
+
+    /// Returns the product DFA accepting the strings accepted by both DFAs.
+    pub fn intersect(&self, other: &DFA) -> Self {
+        let alphabet = self.alphabet();
+        let mut dfa = Self::default();
+        let mut ids: HashMap<(u32, u32), u32> = HashMap::new();
+        let mut pending = vec![(0, 0)];
+        ids.insert((0, 0), 0);
+        dfa.state_count = 1;
+
+        while let Some((left, right)) = pending.pop() {
+            let id = ids[&(left, right)];
+            if self.accept.contains(&left) && other.accept.contains(&right) {
+                dfa.accept.insert(id);
+            }
+            for &c in &alphabet {
+                let (Some(&next_left), Some(&next_right)) = (
+                    self.transition_function.get(&(left, c)),
+                    other.transition_function.get(&(right, c)),
+                ) else {
+                    continue;
+                };
+                let next_id = *ids.entry((next_left, next_right)).or_insert_with(|| {
+                    pending.push((next_left, next_right));
+                    dfa.state_count += 1;
+                    dfa.state_count - 1
+                });
+                dfa.transition_function.insert((id, c), next_id);
+            }
+        }
+
+        dfa
+    }
+
+    /// Returns true if the DFA accepts no string at all.
+    pub fn is_empty(&self) -> bool {
+        let mut visited = HashSet::from([0]);
+        let mut pending = vec![0];
+        while let Some(state) = pending.pop() {
+            if self.accept.contains(&state) {
+                return false;
+            }
+            for (&(from, _), &next) in &self.transition_function {
+                if from == state && visited.insert(next) {
+                    pending.push(next);
+                }
+            }
+        }
+        true
+    }
+
+    /// Returns a shortest string accepted by exactly one of the two DFAs, or `None` if they accept the same language.
+    ///
+    /// This is a breadth-first search of the symmetric difference of the DFAs, ties being broken
+    /// by character order.
+    pub fn shortest_difference(&self, other: &DFA) -> Option<String> {
+        let mut alphabet = self.alphabet();
+        alphabet.extend(other.alphabet());
+        alphabet.sort_unstable();
+        alphabet.dedup();
+
+        // `None` stands for the dead state of a DFA, which rejects every input
+        let accepts =
+            |dfa: &DFA, state: Option<u32>| state.is_some_and(|s| dfa.accept.contains(&s));
+        let next = |dfa: &DFA, state: Option<u32>, c: char| {
+            state.and_then(|s| dfa.transition_function.get(&(s, c)).copied())
+        };
+
+        let mut parents: HashMap<StatePair, Option<(StatePair, char)>> =
+            HashMap::from([((Some(0), Some(0)), None)]);
+        let mut pending: VecDeque<StatePair> = VecDeque::from([(Some(0), Some(0))]);
+        while let Some(pair) = pending.pop_front() {
+            if accepts(self, pair.0) != accepts(other, pair.1) {
+                let mut witness = Vec::new();
+                let mut current = pair;
+                while let Some((parent, c)) = parents[&current] {
+                    witness.push(c);
+                    current = parent;
+                }
+                return Some(witness.into_iter().rev().collect());
+            }
+            for &c in &alphabet {
+                let next_pair = (next(self, pair.0, c), next(other, pair.1, c));
+                if next_pair != (None, None) && !parents.contains_key(&next_pair) {
+                    parents.insert(next_pair, Some((pair, c)));
+                    pending.push_back(next_pair);
+                }
+            }
+        }
+        None
+    }
+
+    /// Exports the DFA as a [DfaTable].
+    pub fn to_table(&self) -> DfaTable {
+        let alphabet = self.alphabet();
+
+        let mut transitions = Vec::with_capacity(self.state_count as usize * alphabet.len());
+        for state in 0..self.state_count {
+            for &c in &alphabet {
+                transitions.push(self.transition_function.get(&(state, c)).copied());
+            }
+        }
+
+        DfaTable {
+            state_count: self.state_count,
+            accept: (0..self.state_count)
+                .map(|state| self.accept.contains(&state))
+                .collect(),
+            alphabet,
+            transitions,
+        }
+    }
+
+    /// Reconstructs a DFA from a [DfaTable].
+    pub fn from_table(table: &DfaTable) -> Self {
+        let width = table.alphabet.len();
+        let mut dfa = Self {
+            state_count: table.state_count,
+            ..Self::default()
+        };
+        for (state, &accepting) in table.accept.iter().enumerate() {
+            if accepting {
+                dfa.accept.insert(state as u32);
+            }
+        }
+        for (i, next) in table.transitions.iter().enumerate() {
+            if let Some(next) = next {
+                let state = (i / width) as u32;
+                dfa.transition_function
+                    .insert((state, table.alphabet[i % width]), *next);
+            }
+        }
+        dfa
+    }
+}
+
+impl DfaTable {
+    /// Serializes the table to JSON. Missing transitions are written as `-1`.
+    pub fn to_json(&self) -> String {
+        let alphabet: Vec<String> = self.alphabet.iter().map(|&c| json_string(c)).collect();
+        let transitions: Vec<String> = self.transitions.iter().map(transition_cell).collect();
+        let accept: Vec<&str> = self
+            .accept
+            .iter()
+            .map(|&a| if a { "true" } else { "false" })
+            .collect();
+        format!(
+            "{{\"state_count\":{},\"alphabet\":[{}],\"transitions\":[{}],\"accept\":[{}]}}",
+            self.state_count,
+            alphabet.join(","),
+            transitions.join(","),
+            accept.join(",")
+        )
+    }
+
+    /// Serializes the table to CSV, one row per state. The header row holds `state`, the
+    /// alphabet as Unicode scalar values and `accept`. Missing transitions are written as `-1`.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("state");
+        for c in &self.alphabet {
+            csv.push_str(&format!(",{}", *c as u32));
+        }
+        csv.push_str(",accept\n");
+
+        let width = self.alphabet.len();
+        for state in 0..self.state_count as usize {
+            csv.push_str(&state.to_string());
+            for cell in &self.transitions[state * width..(state + 1) * width] {
+                csv.push(',');
+                csv.push_str(&transition_cell(cell));
+            }
+            csv.push_str(if self.accept[state] { ",1\n" } else { ",0\n" });
+        }
+        csv
+    }
+}
+
+/// A state of each of two DFAs, `None` standing for the dead state.
+type StatePair = (Option<u32>, Option<u32>);
+
+/// Returns the JSON string holding the character. Only `"`, `\\` and the control characters are
+/// escaped, other characters being written as is.
+fn json_string(c: char) -> String {
+    match c {
+        '"' => "\"\\\"\"".to_string(),
+        '\\' => "\"\\\\\"".to_string(),
+        c if c.is_control() => {
+            format!("\"\\u{:04x}\"", c as u32)
+        }
+        c => format!("\"{}\"", c),
+    }
+}
+
+fn transition_cell(next: &Option<u32>) -> String {
+    next.map_or_else(|| "-1".to_string(), |next| next.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translation::node::Node;
+    use crate::translation::operator::Operator;
+
+    /// `(a|b)*c`
+    fn sample_nfa() -> NFA {
+        let tree = Node::Operation(
+            Operator::Concat,
+            Box::new(Node::Operation(
+                Operator::Production,
+                Box::new(Node::Operation(
+                    Operator::Or,
+                    Box::new(Node::Terminal('a', 1)),
+                    Some(Box::new(Node::Terminal('b', 2))),
+                )),
+                None,
+            )),
+            Some(Box::new(Node::Terminal('c', 3))),
+        );
+        crate::regex(&tree)
+    }
+
+    #[test]
+    fn from_nfa_test() {
+        let nfa = sample_nfa();
+        let dfa = DFA::from_nfa(&nfa);
+        for input in ["c", "ac", "abbac", "", "a", "ca", "acc"] {
+            assert_eq!(dfa.run(input), nfa.run(input), "input {:?}", input);
+        }
+    }
+
+    #[test]
+    fn table_round_trip_test() {
+        let dfa = DFA::from_nfa(&sample_nfa());
+        let table = dfa.to_table();
+        assert_eq!(table.alphabet, vec!['a', 'b', 'c']);
+        assert_eq!(table.transitions.len(), table.state_count as usize * 3);
+        assert_eq!(DFA::from_table(&table), dfa);
+    }
+
+    #[test]
+    fn equivalence_classes_test() {
+        // The start state and the states after `a` and `b` all expect the same suffixes
+        let dfa = DFA::from_nfa(&sample_nfa());
+        assert_eq!(dfa.state_count, 4);
+        let classes = dfa.equivalence_classes();
+        assert_eq!(classes.len(), 2);
+        assert!(classes[0].contains(&0));
+        assert_eq!(classes[0].len(), 3);
+        assert_eq!(classes[1], dfa.accept);
+
+        // In `ab|cb` the states after `a` and `c` merge, and so do the two final states
+        let tree = Node::Operation(
+            Operator::Or,
+            Box::new(Node::from_str_literal("ab")),
+            Some(Box::new(Node::Operation(
+                Operator::Concat,
+                Box::new(Node::Terminal('c', 3)),
+                Some(Box::new(Node::Terminal('b', 4))),
+            ))),
+        );
+        let classes = DFA::from_nfa(&crate::regex(&tree)).equivalence_classes();
+        let sizes: Vec<usize> = classes.iter().map(HashSet::len).collect();
+        assert_eq!(sizes, vec![1, 2, 2]);
+    }
+
+    #[test]
+    fn complement_test() {
+        let dfa = DFA::from_nfa(&sample_nfa());
+        let complement = dfa.complement(&dfa.alphabet());
+        for input in ["c", "abc", "", "a", "ca"] {
+            assert_ne!(complement.run(input), dfa.run(input), "input {:?}", input);
+        }
+        assert!(!complement.is_empty());
+        assert!(dfa.intersect(&complement).is_empty());
+    }
+
+    #[test]
+    fn table_serialize_test() {
+        let table = DfaTable {
+            state_count: 2,
+            alphabet: vec!['a', '"'],
+            transitions: vec![Some(1), None, None, None],
+            accept: vec![false, true],
+        };
+        assert_eq!(
+            table.to_json(),
+            r#"{"state_count":2,"alphabet":["a","\""],"transitions":[1,-1,-1,-1],"accept":[false,true]}"#
+        );
+        assert_eq!(table.to_csv(), "state,97,34,accept\n0,1,-1,0\n1,-1,-1,1\n");
+    }
+
+    /// Decodes the JSON strings of the `alphabet` array written by [DfaTable::to_json].
+    fn json_alphabet(json: &str) -> Vec<char> {
+        let start = json.find("\"alphabet\":[").unwrap() + "\"alphabet\":[".len();
+        let mut chars = json[start..].chars();
+        let mut alphabet = Vec::new();
+        loop {
+            match chars.next().unwrap() {
+                ']' => return alphabet,
+                ',' => {}
+                '"' => {
+                    let c = match chars.next().unwrap() {
+                        '\\' => match chars.next().unwrap() {
+                            'u' => {
+                                let hex: String = chars.by_ref().take(4).collect();
+                                char::from_u32(u32::from_str_radix(&hex, 16).unwrap()).unwrap()
+                            }
+                            escaped => escaped,
+                        },
+                        c => {
+                            assert!(!c.is_control(), "unescaped control character");
+                            c
+                        }
+                    };
+                    assert_eq!(chars.next(), Some('"'), "one character per string");
+                    alphabet.push(c);
+                }
+                c => panic!("unexpected {:?}", c),
+            }
+        }
+    }
+
+    #[test]
+    fn table_json_escape_test() {
+        let alphabet = vec!['é', '\'', '\\', '"', '\n', '\u{1}', '\u{85}', '日'];
+        let table = DfaTable {
+            state_count: 1,
+            alphabet: alphabet.clone(),
+            transitions: vec![None; alphabet.len()],
+            accept: vec![true],
+        };
+        let json = table.to_json();
+        assert!(json.contains(r#"["é","'","\\","\"","\u000a","\u0001","\u0085","日"]"#));
+        assert_eq!(json_alphabet(&json), alphabet);
+    }
+}
@@ -0,0 +1,239 @@
+//! Contains the `LazyDFA` struct, which determinizes a [NFA] on demand while simulating it.
+
+use crate::nfa::NFA;
+use std::collections::{BTreeSet, HashMap};
+
+/// The `LazyDFA` struct simulates a [NFA] like a [crate::dfa::DFA] built during the simulation.
+///
+/// Every set of NFA states met is given a DFA state, and every transition taken is cached, so long
+/// inputs mostly run on cached transitions. At most `capacity` DFA states are kept: once the cache
+/// is full it is cleared, keeping only the start state, and filled again from the current state,
+/// so memory stays bounded for patterns whose DFA would blow up. With a capacity of `1` there is no
+/// room left after the start state, so the rest of the input is simulated by walking the NFA state
+/// sets without caching them.
+#[derive(Debug)]
+pub struct LazyDFA<'a> {
+    nfa: &'a NFA,
+    capacity: usize,
+    stats: DfaCacheStats,
+    /// Map from a set of NFA states to its DFA state.
+    ids: HashMap<BTreeSet<u32>, u32>,
+    /// `sets[s]` is the set of NFA states of DFA state `s`.
+    sets: Vec<BTreeSet<u32>>,
+    /// `accept[s]` is true if DFA state `s` is accepting.
+    accept: Vec<bool>,
+    /// The cached transitions, from a pair of a DFA state and a character to the next DFA state.
+    transitions: HashMap<(u32, char), u32>,
+}
+
+/// The `DfaCacheStats` struct counts how well the cache of a [LazyDFA] serves its inputs, to tune its capacity.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DfaCacheStats {
+    /// Number of characters read on a cached transition.
+    pub hits: u64,
+    /// Number of characters whose transition had to be computed from the NFA.
+    pub misses: u64,
+    /// Number of times the cache was full and cleared.
+    pub evictions: u64,
+    /// Number of DFA states currently cached.
+    pub states: usize,
+}
+
+impl<'a> LazyDFA<'a> {
+    /// Creates a lazy DFA over the NFA, keeping at most `capacity` DFA states. The start state is
+    /// always cached, so a capacity of `0` behaves like `1`.
+    pub fn new(nfa: &'a NFA, capacity: usize) -> Self {
+        let mut lazy = Self {
+            nfa,
+            capacity: capacity.max(1),
+            stats: DfaCacheStats::default(),
+            ids: HashMap::new(),
+            sets: Vec::new(),
+            accept: Vec::new(),
+            transitions: HashMap::new(),
+        };
+        lazy.add_state(BTreeSet::from([0]));
+        lazy
+    }
+
+    /// Returns the number of DFA states currently cached.
+    pub fn cached_states(&self) -> usize {
+        self.sets.len()
+    }
+
+    /// Returns the cache counters, accumulated over all the inputs run so far.
+    pub fn stats(&self) -> DfaCacheStats {
+        DfaCacheStats {
+            states: self.sets.len(),
+            ..self.stats
+        }
+    }
+
+    /// Simulates the NFA with the given input, building and caching DFA states as they are reached.
+    ///
+    /// Like [NFA::run], inputs longer than the maximum input length of the NFA are rejected.
+    pub fn run(&mut self, input: impl AsRef<str>) -> bool {
+        let input = input.as_ref();
+        if self.nfa.max_input_len.is_some_and(|max| input.len() > max) {
+            return false;
+        }
+        let mut state = 0;
+        let mut chars = input.chars();
+        while let Some(c) = chars.next() {
+            if let Some(&next) = self.transitions.get(&(state, c)) {
+                self.stats.hits += 1;
+                state = next;
+                continue;
+            }
+            self.stats.misses += 1;
+            let next_set = self.next_set(&self.sets[state as usize], c);
+            if next_set.is_empty() {
+                return false;
+            }
+            let next = match self.ids.get(&next_set) {
+                Some(&next) => next,
+                None if self.sets.len() < self.capacity => self.add_state(next_set),
+                None if self.capacity > 1 => {
+                    // The transition would come from a cleared state, so it is not cached
+                    self.clear();
+                    state = self.add_state(next_set);
+                    continue;
+                }
+                None => {
+                    // The cache is full, finish with the NFA state sets
+                    let mut current_set = next_set;
+                    for c in chars {
+                        current_set = self.next_set(&current_set, c);
+                        if current_set.is_empty() {
+                            return false;
+                        }
+                    }
+                    return current_set
+                        .iter()
+                        .any(|state| self.nfa.accept.contains(state));
+                }
+            };
+            self.transitions.insert((state, c), next);
+            state = next;
+        }
+        self.accept[state as usize]
+    }
+
+    /// Returns the set of NFA states reached from `set` by reading `c`.
+    fn next_set(&self, set: &BTreeSet<u32>, c: char) -> BTreeSet<u32> {
+        set.iter()
+            .filter_map(|&state| self.nfa.transition_function.get(&(state, c)))
+            .flatten()
+            .copied()
+            .collect()
+    }
+
+    /// Empties the cache, keeping only the start state.
+    fn clear(&mut self) {
+        self.stats.evictions += 1;
+        self.ids.clear();
+        self.sets.clear();
+        self.accept.clear();
+        self.transitions.clear();
+        self.add_state(BTreeSet::from([0]));
+    }
+
+    /// Caches a new DFA state for the set of NFA states, returning its id.
+    fn add_state(&mut self, set: BTreeSet<u32>) -> u32 {
+        let id = self.sets.len() as u32;
+        self.accept
+            .push(set.iter().any(|state| self.nfa.accept.contains(state)));
+        self.ids.insert(set.clone(), id);
+        self.sets.push(set);
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translation::node::Node;
+    use crate::translation::operator::Operator;
+
+    /// `(a|b)*a(a|b)`, whose DFA has to remember the last two characters.
+    fn second_to_last_a() -> NFA {
+        let a_or_b = |a, b| {
+            Node::Operation(
+                Operator::Or,
+                Box::new(Node::Terminal('a', a)),
+                Some(Box::new(Node::Terminal('b', b))),
+            )
+        };
+        crate::regex(&Node::Operation(
+            Operator::Concat,
+            Box::new(Node::Operation(
+                Operator::Concat,
+                Box::new(Node::Operation(
+                    Operator::Production,
+                    Box::new(a_or_b(1, 2)),
+                    None,
+                )),
+                Some(Box::new(Node::Terminal('a', 3))),
+            )),
+            Some(Box::new(a_or_b(4, 5))),
+        ))
+    }
+
+    #[test]
+    fn run_test() {
+        let nfa = second_to_last_a();
+        let long = "ab".repeat(500);
+        for capacity in [1, 2, 100] {
+            let mut lazy = LazyDFA::new(&nfa, capacity);
+            for input in [
+                "ab",
+                "aa",
+                "ba",
+                "b",
+                "",
+                "bbab",
+                "abc",
+                &long,
+                &(long.clone() + "b"),
+            ] {
+                assert_eq!(lazy.run(input), nfa.run(input), "input {:?}", input);
+            }
+            assert!(lazy.cached_states() <= capacity);
+        }
+    }
+
+    #[test]
+    fn cache_stats_test() {
+        let nfa = second_to_last_a();
+        let input = "aabbab".repeat(10);
+        let mut lazy = LazyDFA::new(&nfa, 100);
+        assert!(lazy.run(&input));
+        let stats = lazy.stats();
+        assert_eq!(stats.hits + stats.misses, input.len() as u64);
+        assert_eq!(stats.evictions, 0);
+        assert!(stats.misses <= 8);
+        // The second time every transition is cached
+        assert!(lazy.run(&input));
+        assert_eq!(lazy.stats().misses, stats.misses);
+        assert_eq!(lazy.stats().hits, stats.hits + input.len() as u64);
+
+        // The four states do not fit, so the cache is cleared again and again
+        let mut small = LazyDFA::new(&nfa, 2);
+        assert_eq!(small.run(&input), nfa.run(&input));
+        assert_eq!(small.run(input.clone() + "a"), nfa.run(input.clone() + "a"));
+        let stats = small.stats();
+        assert!(stats.evictions > 0);
+        assert!(stats.states <= 2);
+        assert_eq!(stats.hits + stats.misses, 2 * input.len() as u64 + 1);
+    }
+
+    #[test]
+    fn cache_reuse_test() {
+        let nfa = second_to_last_a();
+        let mut lazy = LazyDFA::new(&nfa, 100);
+        // The DFA has at most 4 states, one per pair of last two characters
+        assert!(lazy.run("ab".repeat(1000)));
+        assert!(!lazy.run("ba".repeat(1000)));
+        assert!(lazy.cached_states() <= 4);
+    }
+}
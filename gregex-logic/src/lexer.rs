@@ -0,0 +1,130 @@
+//! Contains the `Lexer` struct, which splits an input into tokens using a list of rules.
+
+use crate::nfa::NFA;
+use std::fmt;
+
+/// The `LexError` enum represents the reasons a [Lexer] can fail to tokenize an input.
+#[derive(Debug, PartialEq, Eq)]
+pub enum LexError {
+    /// No rule matches a non-empty prefix of the input starting at byte `at`.
+    NoRuleMatches { at: usize },
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexError::NoRuleMatches { at } => write!(f, "no rule matches the input at byte {}", at),
+        }
+    }
+}
+
+impl std::error::Error for LexError {}
+
+/// The `Lexer` struct tokenizes an input with an ordered list of `(NFA, kind)` rules.
+///
+/// At every position the rule matching the longest prefix wins, ties going to the rule given first.
+/// Rules only matching the empty string are ignored, so the lexer always makes progress.
+#[derive(Debug)]
+pub struct Lexer<K> {
+    rules: Vec<(NFA, K)>,
+}
+
+impl<K: Clone> Lexer<K> {
+    /// Creates a lexer from its rules, in order of priority.
+    pub fn new(rules: Vec<(NFA, K)>) -> Self {
+        Self { rules }
+    }
+
+    /// Splits the input into `(kind, text)` tokens.
+    pub fn tokenize<'a>(&self, input: &'a str) -> Result<Vec<(K, &'a str)>, LexError> {
+        let mut tokens = Vec::new();
+        let mut at = 0;
+        while at < input.len() {
+            let rest = &input[at..];
+            let mut best: Option<(usize, &K)> = None;
+            for (nfa, kind) in &self.rules {
+                match nfa.longest_prefix(rest) {
+                    Some(len) if len > 0 && best.is_none_or(|(best_len, _)| len > best_len) => {
+                        best = Some((len, kind));
+                    }
+                    _ => {}
+                }
+            }
+            let (len, kind) = best.ok_or(LexError::NoRuleMatches { at })?;
+            tokens.push((kind.clone(), &rest[..len]));
+            at += len;
+        }
+        Ok(tokens)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translation::node::Node;
+    use crate::translation::operator::Operator;
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    enum TokenKind {
+        Letters,
+        Digits,
+    }
+
+    /// NFA of `(x|y)(x|y)*`.
+    fn one_or_more(x: char, y: char) -> NFA {
+        crate::regex(&Node::Operation(
+            Operator::Concat,
+            Box::new(Node::Operation(
+                Operator::Or,
+                Box::new(Node::Terminal(x, 1)),
+                Some(Box::new(Node::Terminal(y, 2))),
+            )),
+            Some(Box::new(Node::Operation(
+                Operator::Production,
+                Box::new(Node::Operation(
+                    Operator::Or,
+                    Box::new(Node::Terminal(x, 3)),
+                    Some(Box::new(Node::Terminal(y, 4))),
+                )),
+                None,
+            ))),
+        ))
+    }
+
+    fn sample_lexer() -> Lexer<TokenKind> {
+        Lexer::new(vec![
+            (one_or_more('a', 'b'), TokenKind::Letters),
+            (one_or_more('1', '2'), TokenKind::Digits),
+        ])
+    }
+
+    #[test]
+    fn tokenize_test() {
+        assert_eq!(
+            sample_lexer().tokenize("ab12"),
+            Ok(vec![(TokenKind::Letters, "ab"), (TokenKind::Digits, "12")])
+        );
+        assert_eq!(sample_lexer().tokenize(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn tokenize_no_rule_test() {
+        assert_eq!(
+            sample_lexer().tokenize("ab3"),
+            Err(LexError::NoRuleMatches { at: 2 })
+        );
+    }
+
+    #[test]
+    fn tokenize_priority_test() {
+        let star_a = crate::regex(&Node::Operation(
+            Operator::Production,
+            Box::new(Node::Terminal('a', 1)),
+            None,
+        ));
+        let lexer = Lexer::new(vec![(star_a, 0), (one_or_more('a', 'b'), 1)]);
+        assert_eq!(lexer.tokenize("aab"), Ok(vec![(1, "aab")]));
+        assert_eq!(lexer.tokenize("aa"), Ok(vec![(0, "aa")]));
+        assert_eq!(lexer.tokenize("b"), Ok(vec![(1, "b")]));
+    }
+}
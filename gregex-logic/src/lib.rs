@@ -1,7 +1,190 @@
-#[doc = include_str!("../README.md")]
-#[cfg(not(doctest))]
+#![doc = include_str!("../README.md")]
+pub mod builder;
+pub mod cached;
+pub mod dfa;
+pub mod lazy;
+pub mod lexer;
+pub mod literals;
 pub mod nfa;
+pub mod parser;
+pub mod regexset;
+pub mod search;
+pub mod symbol;
 pub mod translation;
 
+pub use parser::escape;
+
 use std::sync::atomic::AtomicU32;
 pub static TERMINAL_COUNT: AtomicU32 = AtomicU32::new(0);
+
+use builder::CompileError;
+use nfa::NFA;
+use symbol::Symbol;
+use translation::node::{factors_set, nullability_set, prefix_set, suffix_set, Node};
+use translation::setterminal::SetTerminal;
+
+/// Converts a regular expression tree to its NFA, using the prefix, suffix and factors sets of the tree.
+///
+/// The terminal codes of the tree must be unique and non-zero, see [Node::renumber].
+///
+/// If the tree is an alternation of literals, the NFA searches with a [literals::AhoCorasick] matcher.
+///
+/// # Panics
+///
+/// Panics if an operation of the tree has the wrong number of operands, see [try_compile].
+pub fn regex(regex_tree: &Node) -> NFA {
+    let mut nfa = regex_over(regex_tree);
+    nfa.literals = literals::literal_alternatives(regex_tree)
+        .map(|words| Box::new(literals::AhoCorasick::new(&words)));
+    nfa
+}
+
+/// Converts a regular expression tree over any [Symbol] type to its NFA, like [regex] does for `char`,
+/// but without literal search acceleration.
+pub fn regex_over<S: Symbol>(regex_tree: &Node<S>) -> NFA<S> {
+    let prefix_set = prefix_set(regex_tree);
+    let suffix_set = suffix_set(regex_tree);
+    let factors_set = factors_set(regex_tree);
+    let mut nfa = NFA::set_to_nfa(&prefix_set, &suffix_set, &factors_set);
+    if nullability_set(regex_tree).contains(&SetTerminal::Epsilon) {
+        nfa.accept.insert(0);
+    }
+    nfa.tree_depth = regex_tree.depth();
+    nfa.min_len = nfa.match_len_bounds().0;
+    nfa
+}
+
+/// Converts a regular expression tree to its NFA like [regex], but returns an error instead of
+/// panicking when the construction cannot handle the tree.
+pub fn try_compile(regex_tree: &Node) -> Result<NFA, CompileError> {
+    check_operands(regex_tree)?;
+    Ok(regex(regex_tree))
+}
+
+/// Returns an error if an operation of the tree has the wrong number of operands, like an `Or`
+/// without its right operand or a star with one.
+pub(crate) fn check_operands<S>(regex_tree: &Node<S>) -> Result<(), CompileError> {
+    let mut pending = vec![regex_tree];
+    while let Some(node) = pending.pop() {
+        if let Node::Operation(op, left, right) = node {
+            if op.is_binary() != right.is_some() {
+                return Err(CompileError::MalformedOperation(*op));
+            }
+            pending.push(left);
+            pending.extend(right.as_deref());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+    use translation::operator::Operator;
+
+    #[test]
+    fn try_compile_test() {
+        // ab+
+        let tree = Node::Operation(
+            Operator::Concat,
+            Box::new(Node::Terminal('a', 1)),
+            Some(Box::new(Node::Operation(
+                Operator::Plus,
+                Box::new(Node::Terminal('b', 2)),
+                None,
+            ))),
+        );
+        let nfa = try_compile(&tree).unwrap();
+        assert!(nfa.run("abb"));
+        assert!(!nfa.run("a"));
+
+        let tree = Node::Operation(Operator::Production, Box::new(Node::Terminal('a', 1)), None);
+        assert!(try_compile(&tree).unwrap().run("aa"));
+    }
+
+    #[test]
+    fn try_compile_malformed_test() {
+        // An alternation missing its right operand, deep inside the tree
+        let tree = Node::Operation(
+            Operator::Concat,
+            Box::new(Node::Terminal('a', 1)),
+            Some(Box::new(Node::Operation(
+                Operator::Production,
+                Box::new(Node::Operation(
+                    Operator::Or,
+                    Box::new(Node::Terminal('b', 2)),
+                    None,
+                )),
+                None,
+            ))),
+        );
+        assert_eq!(
+            try_compile(&tree).unwrap_err(),
+            CompileError::MalformedOperation(Operator::Or)
+        );
+        // A concatenation without a right operand would otherwise recurse forever
+        let tree = Node::Operation(Operator::Concat, Box::new(Node::Terminal('a', 1)), None);
+        assert_eq!(
+            builder::RegexBuilder::new().build(tree).unwrap_err(),
+            CompileError::MalformedOperation(Operator::Concat)
+        );
+        let tree = Node::Operation(
+            Operator::Question,
+            Box::new(Node::Terminal('a', 1)),
+            Some(Box::new(Node::Terminal('b', 2))),
+        );
+        assert_eq!(
+            try_compile(&tree).unwrap_err(),
+            CompileError::MalformedOperation(Operator::Question)
+        );
+    }
+
+    #[test]
+    fn single_terminal_test() {
+        let tree = Node::Terminal('a', 1);
+        assert_eq!(
+            prefix_set(&tree),
+            HashSet::from([SetTerminal::SingleElement('a', 1)])
+        );
+        assert_eq!(
+            suffix_set(&tree),
+            HashSet::from([SetTerminal::SingleElement('a', 1)])
+        );
+        assert_eq!(factors_set(&tree), HashSet::from([SetTerminal::Empty]));
+
+        let nfa = regex(&tree);
+        assert_eq!(
+            nfa.transition_function,
+            HashMap::from([((0, 'a'), HashSet::from([1]))])
+        );
+        assert_eq!(nfa.accept, HashSet::from([1]));
+        assert!(nfa.run("a"));
+        assert!(!nfa.run(""));
+        assert!(!nfa.run("aa"));
+        assert!(!nfa.run("b"));
+    }
+
+    #[test]
+    fn transition_table_test() {
+        // Linearized regex: (a1(a2b3)*)* + (b4a5)*
+        let tree = parser::parse("(a(ab)*)*|(ba)*").unwrap();
+        let expected: HashMap<(u32, char), HashSet<u32>> = HashMap::from([
+            ((0, 'a'), HashSet::from([1])),
+            ((0, 'b'), HashSet::from([4])),
+            ((1, 'a'), HashSet::from([1, 2])),
+            ((2, 'b'), HashSet::from([3])),
+            ((3, 'a'), HashSet::from([1, 2])),
+            ((4, 'a'), HashSet::from([5])),
+            ((5, 'b'), HashSet::from([4])),
+        ]);
+        // The sets are hashed with a new random seed every time, so compiling again iterates them
+        // in another order
+        for _ in 0..20 {
+            let nfa = regex(&tree);
+            assert_eq!(nfa.transition_function, expected);
+            assert_eq!(nfa.accept, HashSet::from([0, 1, 3, 5]));
+            assert_eq!(nfa.states, HashSet::from([0, 1, 2, 3, 4, 5]));
+        }
+    }
+}
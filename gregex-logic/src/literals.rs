@@ -0,0 +1,230 @@
+//! Contains the `AhoCorasick` struct, which searches for many literal strings at once.
+
+use crate::search::MatchSemantics;
+use crate::translation::node::Node;
+use crate::translation::operator::Operator;
+use std::collections::{HashMap, VecDeque};
+
+/// The `AhoCorasick` struct finds the occurrences of a set of words in a single pass over a
+/// haystack, using a trie of the words with failure links.
+///
+/// [crate::regex] builds one for trees that are an alternation of literals, which [crate::nfa::NFA::find]
+/// and the other search methods then use instead of the NFA.
+#[derive(Debug)]
+pub struct AhoCorasick {
+    /// Transitions of every trie node, node `0` being the root.
+    goto: Vec<HashMap<char, usize>>,
+    /// Node reached when the next character has no transition, the longest proper suffix in the trie.
+    fail: Vec<usize>,
+    /// Byte lengths of the words ending at every node, suffixes included.
+    outputs: Vec<Vec<usize>>,
+    /// Byte length of the longest word.
+    max_len: usize,
+}
+
+impl AhoCorasick {
+    /// Builds the matcher of the given words.
+    pub fn new<W: AsRef<str>>(words: &[W]) -> Self {
+        let mut matcher = Self {
+            goto: vec![HashMap::new()],
+            fail: vec![0],
+            outputs: vec![Vec::new()],
+            max_len: 0,
+        };
+        for word in words {
+            let word = word.as_ref();
+            let mut node = 0;
+            for c in word.chars() {
+                node = match matcher.goto[node].get(&c) {
+                    Some(&next) => next,
+                    None => {
+                        matcher.goto.push(HashMap::new());
+                        matcher.fail.push(0);
+                        matcher.outputs.push(Vec::new());
+                        let next = matcher.goto.len() - 1;
+                        matcher.goto[node].insert(c, next);
+                        next
+                    }
+                };
+            }
+            matcher.outputs[node].push(word.len());
+            matcher.max_len = matcher.max_len.max(word.len());
+        }
+
+        // Breadth-first, so the failure node of every node is done before the node itself
+        let mut pending: VecDeque<usize> = matcher.goto[0].values().copied().collect();
+        while let Some(node) = pending.pop_front() {
+            let children: Vec<(char, usize)> =
+                matcher.goto[node].iter().map(|(&c, &n)| (c, n)).collect();
+            for (c, child) in children {
+                let mut fallback = matcher.fail[node];
+                let fail = loop {
+                    if let Some(&next) = matcher.goto[fallback].get(&c) {
+                        break next;
+                    }
+                    if fallback == 0 {
+                        break 0;
+                    }
+                    fallback = matcher.fail[fallback];
+                };
+                matcher.fail[child] = fail;
+                let inherited = matcher.outputs[fail].clone();
+                matcher.outputs[child].extend(inherited);
+                pending.push_back(child);
+            }
+        }
+        matcher
+    }
+
+    /// Returns the byte range of every occurrence of the words in the haystack, overlapping ones
+    /// included, sorted by end then by start.
+    pub fn find_all(&self, haystack: &str) -> Vec<(usize, usize)> {
+        let mut found = Vec::new();
+        let mut node = 0;
+        for (i, c) in haystack.char_indices() {
+            node = self.next(node, c);
+            let end = i + c.len_utf8();
+            let mut starts: Vec<usize> = self.outputs[node].iter().map(|len| end - len).collect();
+            starts.sort_unstable();
+            found.extend(starts.into_iter().map(|start| (start, end)));
+        }
+        found
+    }
+
+    /// Returns the byte range of the leftmost occurrence starting at or after `at`, the longest or
+    /// the shortest one at that position depending on `semantics`.
+    pub(crate) fn find_at(
+        &self,
+        haystack: &str,
+        at: usize,
+        semantics: MatchSemantics,
+    ) -> Option<(usize, usize)> {
+        let mut best: Option<(usize, usize)> = None;
+        let mut node = 0;
+        for (i, c) in haystack[at..].char_indices() {
+            let i = at + i;
+            // No occurrence ending from here can start before the best one
+            if best.is_some_and(|(start, _)| i >= start + self.max_len) {
+                break;
+            }
+            node = self.next(node, c);
+            let end = i + c.len_utf8();
+            // The occurrences starting at a given position are found shortest first
+            for len in &self.outputs[node] {
+                let candidate = (end - len, end);
+                best = match best {
+                    Some(current) if candidate.0 > current.0 => Some(current),
+                    Some(current)
+                        if candidate.0 == current.0
+                            && semantics == MatchSemantics::LeftmostShortest =>
+                    {
+                        Some(current)
+                    }
+                    _ => Some(candidate),
+                };
+            }
+        }
+        best
+    }
+
+    /// Returns the node reached from `node` by reading `c`, following the failure links.
+    fn next(&self, mut node: usize, c: char) -> usize {
+        loop {
+            if let Some(&next) = self.goto[node].get(&c) {
+                return next;
+            }
+            if node == 0 {
+                return 0;
+            }
+            node = self.fail[node];
+        }
+    }
+}
+
+/// Returns the words of the tree if it is an alternation of non-empty literals, or a single one.
+pub(crate) fn literal_alternatives(regex_tree: &Node) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut branches = vec![regex_tree];
+    while let Some(branch) = branches.pop() {
+        if let Node::Operation(Operator::Or, left, Some(right)) = branch {
+            branches.push(right);
+            branches.push(left);
+            continue;
+        }
+        let mut word = String::new();
+        let mut pending = vec![branch];
+        while let Some(node) = pending.pop() {
+            match node {
+                Node::Terminal(c, _) => word.push(*c),
+                Node::Operation(Operator::Concat, left, Some(right)) => {
+                    pending.push(right);
+                    pending.push(left);
+                }
+                _ => return None,
+            }
+        }
+        words.push(word);
+    }
+    Some(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_all_test() {
+        let matcher = AhoCorasick::new(&["he", "she", "his", "hers"]);
+        assert_eq!(matcher.find_all("ushers"), vec![(1, 4), (2, 4), (2, 6)]);
+        assert_eq!(matcher.find_all("xyz"), vec![]);
+    }
+
+    #[test]
+    fn literal_alternatives_test() {
+        let tree = crate::parser::parse("cat|dog|c").unwrap();
+        assert_eq!(
+            literal_alternatives(&tree),
+            Some(vec!["cat".to_string(), "dog".to_string(), "c".to_string()])
+        );
+        assert_eq!(
+            literal_alternatives(&crate::parser::parse("ca*t").unwrap()),
+            None
+        );
+        assert_eq!(
+            literal_alternatives(&crate::parser::parse("a|()").unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn dispatch_test() {
+        let words = ["cat", "category", "dog", "do", "gory", "é"];
+        let tree = crate::parser::parse(&words.join("|")).unwrap();
+        let literal = crate::regex(&tree);
+        let general = crate::regex_over(&tree);
+        assert!(literal.literals.is_some());
+        assert!(general.literals.is_none());
+        for haystack in [
+            "the category of dogs",
+            "docat",
+            "é dogory",
+            "nothing",
+            "",
+            "catdo",
+        ] {
+            let literal_spans: Vec<_> = literal.find_iter(haystack).map(|m| m.range()).collect();
+            let general_spans: Vec<_> = general.find_iter(haystack).map(|m| m.range()).collect();
+            assert_eq!(literal_spans, general_spans, "in {:?}", haystack);
+        }
+        let literal = literal.with_match_semantics(MatchSemantics::LeftmostShortest);
+        let general = general.with_match_semantics(MatchSemantics::LeftmostShortest);
+        for haystack in ["the category of dogs", "docat"] {
+            assert_eq!(
+                literal.find(haystack),
+                general.find(haystack),
+                "in {:?}",
+                haystack
+            );
+        }
+    }
+}
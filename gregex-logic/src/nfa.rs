@@ -1,93 +1,1044 @@
 //! Has the implementation of a non-deterministic finite automaton (NFA).
 
+use crate::builder::CompileError;
+use crate::dfa::DFA;
+use crate::literals::AhoCorasick;
+use crate::parser::{escape, parse, parse_bytes, ParseError};
+use crate::search::MatchSemantics;
+use crate::symbol::Symbol;
+use crate::translation::node::{
+    balanced, factors_set, nullability_set, prefix_set, suffix_set, Node,
+};
+use crate::translation::operator::Operator;
 use crate::translation::setterminal::SetTerminal;
-use core::panic;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::io::{self, BufRead};
+use std::time::Instant;
+
+/// Number of characters [NFA::run_until] reads between two checks of its deadline.
+pub const DEADLINE_CHECK_INTERVAL: usize = 64;
+
+/// Length of the longest inputs [NFA::ambiguity_warning] tries.
+pub const AMBIGUITY_CHECK_LEN: usize = 4;
+
+/// The `InputError` enum represents the reasons an input can be refused by [NFA::try_run] and
+/// [NFA::try_run_bounded].
+#[derive(Debug, PartialEq, Eq)]
+pub enum InputError {
+    /// The input has a character for which the NFA has no transition at all, so it can never match.
+    /// `at` is the byte offset of the character in the input.
+    CharNotInAlphabet { ch: char, at: usize },
+    /// The input is longer in bytes than the maximum length.
+    TooLong { len: usize, max: usize },
+    /// The simulation used up its step budget before the character at byte `at`.
+    OutOfSteps { at: usize },
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::CharNotInAlphabet { ch, at } => {
+                write!(
+                    f,
+                    "character {:?} at byte {} is not in the pattern alphabet",
+                    ch, at
+                )
+            }
+            InputError::TooLong { len, max } => {
+                write!(f, "the input is {} bytes long, more than {}", len, max)
+            }
+            InputError::OutOfSteps { at } => {
+                write!(f, "the step budget ran out at byte {}", at)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+/// The `RunDiagnostics` struct is returned by [NFA::run_strict], telling why an input did not match.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RunDiagnostics {
+    /// True if the NFA matches the input.
+    pub matched: bool,
+    /// The byte offset and the character of every character of the input not in the alphabet of the NFA.
+    pub unknown_chars: Vec<(usize, char)>,
+    /// The byte offset and the character after which no state was active anymore, if any.
+    pub stuck_at: Option<(usize, char)>,
+}
 
 /// The `NFA` struct represents a non-deterministic finite automaton.
-#[derive(Debug, Default)]
-pub struct NFA {
+///
+/// Its transitions are on `char` by default, but can be on any [Symbol], see [crate::regex_over].
+#[derive(Debug)]
+pub struct NFA<S = char> {
     /// Set of all possible states of the NFA.
-    states: HashSet<u32>,
+    pub(crate) states: HashSet<u32>,
     /// Set of all accepting states. If the NFA ends at any one if these the simulation is succesful.
-    accept: HashSet<u32>,
+    pub(crate) accept: HashSet<u32>,
     /// The transition function is a map from a pair of a state and a character to a set of states.
-    transition_function: HashMap<(u32, char), HashSet<u32>>,
+    pub(crate) transition_function: HashMap<(u32, S), HashSet<u32>>,
+    /// Which match [NFA::find] and the other search methods report.
+    pub(crate) semantics: MatchSemantics,
+    /// Inputs longer than this many bytes are rejected by [NFA::run] without being simulated.
+    pub(crate) max_input_len: Option<usize>,
+    /// Depth of the tree the NFA was built from, `0` if it was not built from a tree.
+    pub(crate) tree_depth: usize,
+    /// Length of the shortest accepted input, inputs with fewer bytes are rejected without being simulated.
+    pub(crate) min_len: usize,
+    /// Matcher used by the search methods when the pattern is an alternation of literals.
+    pub(crate) literals: Option<Box<AhoCorasick>>,
 }
 
-impl NFA {
-    /// Simulates the NFA with the given input.
-    pub fn run(&self, input: &str) -> bool {
+/// The `Diagnostics` struct describes how a pattern was compiled, see [NFA::new_verbose].
+///
+/// Terminals are pairs of a character and its code in the linearized pattern, and all lists are sorted.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Diagnostics {
+    /// The pattern with every terminal followed by its code, see [Node::linearized].
+    pub linearized: String,
+    /// True if the pattern matches the empty string.
+    pub nullable: bool,
+    /// Terminals a match can start with.
+    pub prefix: Vec<(char, u32)>,
+    /// Terminals a match can end with.
+    pub suffix: Vec<(char, u32)>,
+    /// Pairs of terminals that can follow each other.
+    pub factors: Vec<((char, u32), (char, u32))>,
+    /// Size of the resulting NFA.
+    pub stats: RegexStats,
+}
+
+/// The `RegexStats` struct sums up the size of a [NFA], see [NFA::stats].
+#[derive(Debug, PartialEq, Eq)]
+pub struct RegexStats {
+    /// Number of states, the start state included.
+    pub states: usize,
+    /// Number of transitions, counting every target state apart.
+    pub transitions: usize,
+    /// Number of accepting states.
+    pub accept_states: usize,
+    /// True if the NFA matches the empty string.
+    pub nullable: bool,
+    /// Number of distinct symbols having at least one transition.
+    pub alphabet_size: usize,
+    /// Depth of the tree the NFA was built from, see [Node::depth].
+    pub max_depth: usize,
+}
+
+impl<S> Default for NFA<S> {
+    fn default() -> Self {
+        Self {
+            states: HashSet::new(),
+            accept: HashSet::new(),
+            transition_function: HashMap::new(),
+            semantics: MatchSemantics::default(),
+            max_input_len: None,
+            tree_depth: 0,
+            min_len: 0,
+            literals: None,
+        }
+    }
+}
+
+impl<S: Symbol> NFA<S> {
+    /// Simulates the NFA with the given sequence of symbols.
+    pub fn run_symbols<I: IntoIterator<Item = S>>(&self, input: I) -> bool {
         let mut current_states = HashSet::new();
         current_states.insert(0);
-        for c in input.chars() {
-            let mut next_states = HashSet::new();
-            for state in current_states {
-                if let Some(states) = self.transition_function.get(&(state, c)) {
-                    next_states.extend(states);
+        for symbol in input {
+            current_states = self.step(&current_states, symbol);
+        }
+        !current_states.is_disjoint(&self.accept)
+    }
+
+    /// Returns the size of the NFA, for monitoring the patterns compiled by a program.
+    pub fn stats(&self) -> RegexStats {
+        let alphabet: HashSet<&S> = self.transition_function.keys().map(|(_, c)| c).collect();
+        RegexStats {
+            states: self.states.len(),
+            transitions: self.transition_function.values().map(HashSet::len).sum(),
+            accept_states: self.accept.len(),
+            nullable: self.accept.contains(&0),
+            alphabet_size: alphabet.len(),
+            max_depth: self.tree_depth,
+        }
+    }
+
+    /// Returns an equivalent NFA without the states unreachable from the start state, nor the
+    /// states from which no accepting state is reachable. The start state is always kept.
+    pub fn trim(&self) -> Self {
+        let mut successors: HashMap<u32, Vec<u32>> = HashMap::new();
+        let mut predecessors: HashMap<u32, Vec<u32>> = HashMap::new();
+        for ((from, _), to) in &self.transition_function {
+            for &next in to {
+                successors.entry(*from).or_default().push(next);
+                predecessors.entry(next).or_default().push(*from);
+            }
+        }
+
+        let mut reachable = HashSet::from([0]);
+        let mut pending = vec![0];
+        while let Some(state) = pending.pop() {
+            for &next in successors.get(&state).into_iter().flatten() {
+                if reachable.insert(next) {
+                    pending.push(next);
                 }
             }
-            current_states = next_states;
         }
-        !current_states.is_disjoint(&self.accept)
+
+        let mut useful: HashSet<u32> = self.accept.intersection(&reachable).copied().collect();
+        let mut pending: Vec<u32> = useful.iter().copied().collect();
+        while let Some(state) = pending.pop() {
+            for &from in predecessors.get(&state).into_iter().flatten() {
+                if reachable.contains(&from) && useful.insert(from) {
+                    pending.push(from);
+                }
+            }
+        }
+        useful.insert(0);
+
+        let mut nfa = Self {
+            states: useful.clone(),
+            accept: self.accept.intersection(&useful).copied().collect(),
+            semantics: self.semantics,
+            max_input_len: self.max_input_len,
+            tree_depth: self.tree_depth,
+            min_len: self.min_len,
+            ..Self::default()
+        };
+        for ((from, symbol), to) in &self.transition_function {
+            if !useful.contains(from) {
+                continue;
+            }
+            let to: HashSet<u32> = to.intersection(&useful).copied().collect();
+            if !to.is_empty() {
+                nfa.transition_function.insert((*from, symbol.clone()), to);
+            }
+        }
+        nfa
+    }
+
+    /// Returns the minimum length of the accepted inputs, and their maximum length if it is finite.
+    ///
+    /// For a NFA matching nothing the bounds are `(0, Some(0))`.
+    pub fn match_len_bounds(&self) -> (usize, Option<usize>) {
+        let nfa = self.trim();
+        if nfa.accept.is_empty() {
+            return (0, Some(0));
+        }
+        let mut successors: HashMap<u32, HashSet<u32>> = HashMap::new();
+        for ((from, _), to) in &nfa.transition_function {
+            successors.entry(*from).or_default().extend(to);
+        }
+
+        // Breadth first search for the nearest accepting state
+        let mut min = 0;
+        let mut seen = HashSet::from([0]);
+        let mut current_states = HashSet::from([0]);
+        while current_states.is_disjoint(&nfa.accept) {
+            current_states = current_states
+                .iter()
+                .flat_map(|state| successors.get(state).into_iter().flatten())
+                .copied()
+                .filter(|state| seen.insert(*state))
+                .collect();
+            min += 1;
+        }
+
+        // Every state of the trimmed NFA leads to an accepting state, so any cycle makes the
+        // maximum infinite. Otherwise the longest path is found in topological order.
+        let mut in_degrees: HashMap<u32, usize> =
+            nfa.states.iter().map(|&state| (state, 0)).collect();
+        for to in successors.values() {
+            for state in to {
+                *in_degrees.entry(*state).or_default() += 1;
+            }
+        }
+        let mut longest: HashMap<u32, usize> = HashMap::from([(0, 0)]);
+        let mut pending: Vec<u32> = in_degrees
+            .iter()
+            .filter(|(_, degree)| **degree == 0)
+            .map(|(state, _)| *state)
+            .collect();
+        let mut sorted = 0;
+        while let Some(state) = pending.pop() {
+            sorted += 1;
+            let length = longest.get(&state).copied();
+            for next in successors.get(&state).into_iter().flatten() {
+                if let Some(length) = length {
+                    let entry = longest.entry(*next).or_default();
+                    *entry = (*entry).max(length + 1);
+                }
+                let degree = in_degrees.get_mut(next).unwrap();
+                *degree -= 1;
+                if *degree == 0 {
+                    pending.push(*next);
+                }
+            }
+        }
+        if sorted < in_degrees.len() {
+            return (min, None);
+        }
+        let max = nfa
+            .accept
+            .iter()
+            .filter_map(|state| longest.get(state))
+            .copied()
+            .max();
+        (min, max)
+    }
+
+    /// Returns the set of states reached from `current_states` by reading `c`.
+    fn step(&self, current_states: &HashSet<u32>, c: S) -> HashSet<u32> {
+        let mut next_states = HashSet::new();
+        for state in current_states {
+            if let Some(states) = self.transition_function.get(&(*state, c.clone())) {
+                next_states.extend(states);
+            }
+        }
+        next_states
     }
 
     /// Converts the prefix, suffix and factors sets to a NFA.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a set holds an element of the wrong kind, see [NFA::try_set_to_nfa].
     pub fn set_to_nfa(
-        prefix_set: &HashSet<SetTerminal>,
-        suffix_set: &HashSet<SetTerminal>,
-        factors_set: &HashSet<SetTerminal>,
+        prefix_set: &HashSet<SetTerminal<S>>,
+        suffix_set: &HashSet<SetTerminal<S>>,
+        factors_set: &HashSet<SetTerminal<S>>,
     ) -> Self {
+        Self::try_set_to_nfa(prefix_set, suffix_set, factors_set).expect(
+            "the prefix and suffix sets hold single elements, the factors set double elements",
+        )
+    }
+
+    /// Converts the prefix, suffix and factors sets to a NFA like [NFA::set_to_nfa], but returns
+    /// [CompileError::MalformedSets] if the prefix or suffix set holds a
+    /// [SetTerminal::DoubleElement], or the factors set a [SetTerminal::SingleElement].
+    pub fn try_set_to_nfa(
+        prefix_set: &HashSet<SetTerminal<S>>,
+        suffix_set: &HashSet<SetTerminal<S>>,
+        factors_set: &HashSet<SetTerminal<S>>,
+    ) -> Result<Self, CompileError> {
         let mut nfa = Self::default();
-    
+        nfa.states.insert(0);
+
         for i in prefix_set {
-            match *i {
+            match i {
                 SetTerminal::SingleElement(symbol, index) => {
-                    nfa.states.insert(index);
+                    nfa.states.insert(*index);
                     nfa.transition_function
-                        .insert((0, symbol), vec![index].into_iter().collect());
-                }
-                SetTerminal::DoubleElement(_, _, _, _) => {
-                    panic!("DoubleElement not supported")
+                        .entry((0, symbol.clone()))
+                        .or_default()
+                        .insert(*index);
                 }
+                SetTerminal::DoubleElement(_, _, _, _) => return Err(CompileError::MalformedSets),
                 _ => {}
             }
         }
-    
+
         for i in suffix_set {
-            match *i {
+            match i {
                 SetTerminal::SingleElement(_, index) => {
-                    nfa.states.insert(index);
-                    nfa.accept.insert(index);
-                }
-                SetTerminal::DoubleElement(_, _, _, _) => {
-                    panic!("DoubleElement not supported")
+                    nfa.states.insert(*index);
+                    nfa.accept.insert(*index);
                 }
+                SetTerminal::DoubleElement(_, _, _, _) => return Err(CompileError::MalformedSets),
                 _ => {}
             }
         }
-    
+
         for i in factors_set {
-            match *i {
+            match i {
                 SetTerminal::DoubleElement(_, index1, symbol2, index2) => {
-                    nfa.states.insert(index1);
-                    nfa.states.insert(index2);
-                    nfa.transition_function.entry((index1, symbol2)).or_insert_with(HashSet::new).insert(index2);
-                }
-                SetTerminal::SingleElement(_, _) => {
-                    panic!("SingleElement not supported")
+                    nfa.states.insert(*index1);
+                    nfa.states.insert(*index2);
+                    nfa.transition_function
+                        .entry((*index1, symbol2.clone()))
+                        .or_default()
+                        .insert(*index2);
                 }
+                SetTerminal::SingleElement(_, _) => return Err(CompileError::MalformedSets),
                 _ => {}
             }
         }
-    
+
+        Ok(nfa)
+    }
+}
+
+impl NFA {
+    /// Compiles a pattern string to its NFA, see [crate::parser] for the syntax.
+    pub fn new(pattern: &str) -> Result<Self, ParseError> {
+        Ok(crate::regex(&parse(pattern)?))
+    }
+
+    /// Compiles a pattern string to its NFA like [NFA::new], also returning the linearized pattern,
+    /// its sets and the size of the NFA, to debug why a pattern does not match as expected.
+    pub fn new_verbose(pattern: &str) -> Result<(Self, Diagnostics), ParseError> {
+        let tree = parse(pattern)?;
+        let terminals = |set: HashSet<SetTerminal>| {
+            let mut terminals: Vec<(char, u32)> = set
+                .into_iter()
+                .filter_map(|terminal| match terminal {
+                    SetTerminal::SingleElement(c, code) => Some((c, code)),
+                    _ => None,
+                })
+                .collect();
+            terminals.sort_unstable();
+            terminals
+        };
+        let mut factors: Vec<((char, u32), (char, u32))> = factors_set(&tree)
+            .into_iter()
+            .filter_map(|factor| match factor {
+                SetTerminal::DoubleElement(a, a_code, b, b_code) => {
+                    Some(((a, a_code), (b, b_code)))
+                }
+                _ => None,
+            })
+            .collect();
+        factors.sort_unstable();
+
+        let nfa = crate::regex(&tree);
+        let diagnostics = Diagnostics {
+            linearized: tree.linearized(),
+            nullable: nullability_set(&tree).contains(&SetTerminal::Epsilon),
+            prefix: terminals(prefix_set(&tree)),
+            suffix: terminals(suffix_set(&tree)),
+            factors,
+            stats: nfa.stats(),
+        };
+        Ok((nfa, diagnostics))
+    }
+
+    /// Returns the NFA matching only the empty string: the start state, accepting, and no transitions.
+    pub fn epsilon() -> Self {
+        let mut nfa = Self::never();
+        nfa.accept.insert(0);
         nfa
     }
+
+    /// Returns the NFA matching nothing, not even the empty string: the start state and no transitions.
+    pub fn never() -> Self {
+        let mut nfa = Self::default();
+        nfa.states.insert(0);
+        nfa
+    }
+
+    /// Returns true if the NFA has no accepting state, so it matches no input at all.
+    ///
+    /// When the tree matches the empty string the start state is accepting, so only NFAs like
+    /// [NFA::never] match nothing. Every other tree matches something, so for a NFA built from a
+    /// tree this being true points to a construction bug.
+    pub fn matches_nothing(&self) -> bool {
+        self.accept.is_empty()
+    }
+
+    /// Simulates the NFA with the given input.
+    ///
+    /// If a maximum input length was set with [crate::builder::RegexBuilder::max_input_len], longer
+    /// inputs are rejected right away, like inputs shorter than the minimum of [NFA::match_len_bounds].
+    pub fn run(&self, input: impl AsRef<str>) -> bool {
+        let input = input.as_ref();
+        if self.max_input_len.is_some_and(|max| input.len() > max) || input.len() < self.min_len {
+            return false;
+        }
+        self.run_symbols(input.chars())
+    }
+
+    /// Returns the set of states reached by reading the input from the given states, which is
+    /// empty once no state is active anymore. The start state set is `{0}`, and the input is
+    /// matched if the result contains an accepting state, see [NFA::accept_info].
+    ///
+    /// Running from the states a previous call returned continues the simulation, so an input
+    /// can be matched piece by piece, or the state sets used to build other search strategies.
+    pub fn run_from(&self, states: &HashSet<u32>, input: &str) -> HashSet<u32> {
+        let mut current_states = states.clone();
+        for c in input.chars() {
+            if current_states.is_empty() {
+                break;
+            }
+            current_states = self.step(&current_states, c);
+        }
+        current_states
+    }
+
+    /// Returns true if the input can still be extended to a match, which is useful to validate an
+    /// input while it is being typed.
+    ///
+    /// Every state of a NFA built from a tree leads to an accepting state, so the input is viable
+    /// as long as reading it leaves some state active.
+    pub fn is_viable_prefix(&self, input: impl AsRef<str>) -> bool {
+        let input = input.as_ref();
+        let mut current_states = HashSet::from([0]);
+        for c in input.chars() {
+            current_states = self.step(&current_states, c);
+            if current_states.is_empty() {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Simulates the NFA with the given input, an input character taking every transition whose
+    /// character `eq` considers equal to it, called as `eq(input_char, transition_char)`.
+    ///
+    /// This allows case or accent insensitive matching without recompiling the NFA. Classes like
+    /// `[a-f]` are expanded to their characters when parsed, so they are folded too: a case
+    /// insensitive `[a-f]` matches `C`, and `[Z-a]`, straddling the cases, matches `z`.
+    pub fn run_with<F: Fn(char, char) -> bool>(&self, input: impl AsRef<str>, eq: F) -> bool {
+        let input = input.as_ref();
+        if self.max_input_len.is_some_and(|max| input.len() > max) {
+            return false;
+        }
+        let mut outgoing: HashMap<u32, Vec<(char, &HashSet<u32>)>> = HashMap::new();
+        for (&(from, c), to) in &self.transition_function {
+            outgoing.entry(from).or_default().push((c, to));
+        }
+        let mut current_states: HashSet<u32> = HashSet::from([0]);
+        for c in input.chars() {
+            let mut next_states = HashSet::new();
+            for state in &current_states {
+                for (transition_char, to) in outgoing.get(state).into_iter().flatten() {
+                    if eq(c, *transition_char) {
+                        next_states.extend(*to);
+                    }
+                }
+            }
+            current_states = next_states;
+        }
+        !current_states.is_disjoint(&self.accept)
+    }
+
+    /// Returns the only string the NFA matches, if it matches exactly one, so a plain string
+    /// comparison or substring search can be used instead.
+    ///
+    /// This follows the automaton from the start state, which must be a single chain of
+    /// transitions ending at the only accepting state.
+    pub fn as_literal(&self) -> Option<String> {
+        let mut literal = String::new();
+        let mut state = 0;
+        let mut visited = HashSet::from([0]);
+        loop {
+            let mut outgoing = self
+                .transition_function
+                .iter()
+                .filter(|((from, _), _)| *from == state);
+            if self.accept.contains(&state) {
+                return outgoing.next().is_none().then_some(literal);
+            }
+            let ((_, c), to) = outgoing.next()?;
+            if outgoing.next().is_some() || to.len() != 1 {
+                return None;
+            }
+            literal.push(*c);
+            state = *to.iter().next().unwrap();
+            if !visited.insert(state) {
+                return None;
+            }
+        }
+    }
+
+    /// Returns the byte range of every character of the input if the NFA matches it.
+    ///
+    /// The NFA consumes the whole input along an accepting path, so every character gets a range.
+    pub fn char_match_ranges(&self, input: impl AsRef<str>) -> Option<Vec<(usize, usize)>> {
+        let input = input.as_ref();
+        self.run(input).then(|| {
+            input
+                .char_indices()
+                .map(|(i, c)| (i, i + c.len_utf8()))
+                .collect()
+        })
+    }
+
+    /// Simulates the NFA with the given UTF-16 encoded input, as used by Windows APIs and JavaScript.
+    ///
+    /// An input with a lone surrogate never matches.
+    pub fn run_utf16(&self, input: &[u16]) -> bool {
+        let mut current_states = HashSet::new();
+        current_states.insert(0);
+        for c in char::decode_utf16(input.iter().copied()) {
+            let Ok(c) = c else {
+                return false;
+            };
+            current_states = self.step(&current_states, c);
+        }
+        !current_states.is_disjoint(&self.accept)
+    }
+
+    /// Simulates the NFA with the given input, giving up with `None` once `deadline` has passed.
+    ///
+    /// The clock is only read every [DEADLINE_CHECK_INTERVAL] characters, so the deadline can be
+    /// overrun by that many steps.
+    pub fn run_until(&self, input: impl AsRef<str>, deadline: Instant) -> Option<bool> {
+        let input = input.as_ref();
+        let mut current_states = HashSet::new();
+        current_states.insert(0);
+        for (i, c) in input.chars().enumerate() {
+            if i % DEADLINE_CHECK_INTERVAL == 0 && Instant::now() >= deadline {
+                return None;
+            }
+            current_states = self.step(&current_states, c);
+        }
+        Some(!current_states.is_disjoint(&self.accept))
+    }
+
+    /// Returns the length in bytes of the longest prefix of the input matched by the NFA, if any.
+    pub fn longest_prefix(&self, input: impl AsRef<str>) -> Option<usize> {
+        let input = input.as_ref();
+        let mut current_states = HashSet::new();
+        current_states.insert(0);
+        let mut longest = (!current_states.is_disjoint(&self.accept)).then_some(0);
+        for (i, c) in input.char_indices() {
+            current_states = self.step(&current_states, c);
+            if current_states.is_empty() {
+                break;
+            }
+            if !current_states.is_disjoint(&self.accept) {
+                longest = Some(i + c.len_utf8());
+            }
+        }
+        longest
+    }
+
+    /// Returns the length in bytes of the shortest prefix of the input matched by the NFA, if any.
+    pub fn shortest_prefix(&self, input: impl AsRef<str>) -> Option<usize> {
+        let input = input.as_ref();
+        let mut current_states = HashSet::new();
+        current_states.insert(0);
+        if !current_states.is_disjoint(&self.accept) {
+            return Some(0);
+        }
+        for (i, c) in input.char_indices() {
+            current_states = self.step(&current_states, c);
+            if current_states.is_empty() {
+                break;
+            }
+            if !current_states.is_disjoint(&self.accept) {
+                return Some(i + c.len_utf8());
+            }
+        }
+        None
+    }
+
+    /// Returns the number of distinct accepting runs of the NFA on the input, saturating at [u64::MAX].
+    ///
+    /// A count greater than one means the pattern is ambiguous for this input.
+    pub fn count_paths(&self, input: impl AsRef<str>) -> u64 {
+        let input = input.as_ref();
+        let mut current_paths: HashMap<u32, u64> = HashMap::from([(0, 1)]);
+        for c in input.chars() {
+            current_paths = self.step_paths(&current_paths, c);
+        }
+        self.accepting_paths(&current_paths)
+    }
+
+    /// Returns a warning if some short input is matched along more than one path, see [NFA::count_paths].
+    ///
+    /// Such redundant paths, as in `(a|a)*`, do not slow down the simulation but usually mean the
+    /// pattern has overlapping alternatives. Every input of at most [AMBIGUITY_CHECK_LEN]
+    /// characters over the alphabet of the NFA is tried, shortest first.
+    pub fn ambiguity_warning(&self) -> Option<String> {
+        let alphabet: BTreeSet<char> = self.transition_function.keys().map(|&(_, c)| c).collect();
+        let mut pending: VecDeque<(String, HashMap<u32, u64>)> =
+            VecDeque::from([(String::new(), HashMap::from([(0, 1)]))]);
+        while let Some((input, paths)) = pending.pop_front() {
+            let count = self.accepting_paths(&paths);
+            if count > 1 {
+                return Some(format!(
+                    "the pattern matches {:?} along {} different paths, it may have overlapping alternatives",
+                    input, count
+                ));
+            }
+            if input.chars().count() == AMBIGUITY_CHECK_LEN {
+                continue;
+            }
+            for &c in &alphabet {
+                let next_paths = self.step_paths(&paths, c);
+                if !next_paths.is_empty() {
+                    pending.push_back((format!("{}{}", input, c), next_paths));
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns the number of paths reaching every state after reading `c`, given the number of
+    /// paths reaching every state before.
+    fn step_paths(&self, current_paths: &HashMap<u32, u64>, c: char) -> HashMap<u32, u64> {
+        let mut next_paths: HashMap<u32, u64> = HashMap::new();
+        for (state, paths) in current_paths {
+            for next in self
+                .transition_function
+                .get(&(*state, c))
+                .into_iter()
+                .flatten()
+            {
+                let entry = next_paths.entry(*next).or_default();
+                *entry = entry.saturating_add(*paths);
+            }
+        }
+        next_paths
+    }
+
+    /// Returns the total number of paths reaching an accepting state.
+    fn accepting_paths(&self, paths: &HashMap<u32, u64>) -> u64 {
+        paths
+            .iter()
+            .filter(|(state, _)| self.accept.contains(state))
+            .fold(0, |total, (_, paths)| total.saturating_add(*paths))
+    }
+
+    /// Returns the sorted list of characters having at least one transition.
+    pub fn alphabet(&self) -> Vec<char> {
+        let mut alphabet: Vec<char> = self.transition_function.keys().map(|&(_, c)| c).collect();
+        alphabet.sort_unstable();
+        alphabet.dedup();
+        alphabet
+    }
+
+    /// Returns the sorted set of active states before reading the input and after every character of it.
+    ///
+    /// The trace stops early if no state is active anymore, so it may be shorter than the input.
+    pub fn trace(&self, input: impl AsRef<str>) -> Vec<Vec<u32>> {
+        let input = input.as_ref();
+        let sorted = |states: &HashSet<u32>| {
+            let mut states: Vec<u32> = states.iter().copied().collect();
+            states.sort_unstable();
+            states
+        };
+        let mut current_states = HashSet::from([0]);
+        let mut trace = vec![sorted(&current_states)];
+        for c in input.chars() {
+            current_states = self.step(&current_states, c);
+            if current_states.is_empty() {
+                break;
+            }
+            trace.push(sorted(&current_states));
+        }
+        trace
+    }
+
+    /// Returns every accepting state with the character of its terminal, sorted by state.
+    ///
+    /// In the Glushkov construction every state but the start one stands for a terminal, and all
+    /// transitions into it read its character. The start state, accepting for nullable patterns, has
+    /// no terminal and is left out.
+    pub fn accept_info(&self) -> Vec<(u32, char)> {
+        let mut info: Vec<(u32, char)> = self
+            .transition_function
+            .iter()
+            .flat_map(|(&(_, c), to)| to.iter().map(move |&to| (to, c)))
+            .filter(|(to, _)| self.accept.contains(to))
+            .collect();
+        info.sort_unstable();
+        info.dedup();
+        info
+    }
+
+    /// Returns the transitions reaching more than one state, as `(state, char, target count)`
+    /// sorted by state and character.
+    ///
+    /// These are the branch points where the simulation follows several states at once, and
+    /// where [DFA::from_nfa] creates a state for every reachable set of targets. A Glushkov NFA
+    /// has one state per terminal, so a state branches when several terminals with the same
+    /// character can follow it, like the two `a` of `ab|ac` after the start state.
+    pub fn determinism_report(&self) -> Vec<(u32, char, usize)> {
+        let mut report: Vec<(u32, char, usize)> = self
+            .transition_function
+            .iter()
+            .filter(|(_, to)| to.len() > 1)
+            .map(|(&(from, c), to)| (from, c, to.len()))
+            .collect();
+        report.sort_unstable();
+        report
+    }
+
+    /// Merges the states reading the same character that are equivalent, as they are both accepting
+    /// or not and have the same transitions, until no more states can be merged.
+    ///
+    /// A pattern repeating the same characters, like `(ab|ab)c`, gets a state for every terminal;
+    /// merging them shrinks the NFA without changing the language it matches, more cheaply than
+    /// minimizing its DFA.
+    pub fn merge_equivalent_states(mut self) -> Self {
+        /// Sorted transitions of a state, with their sorted target states.
+        type Transitions = Vec<(char, Vec<u32>)>;
+        loop {
+            let mut incoming: HashMap<u32, char> = HashMap::new();
+            let mut outgoing: HashMap<u32, Transitions> = HashMap::new();
+            for (&(from, c), to) in &self.transition_function {
+                for &state in to {
+                    incoming.insert(state, c);
+                }
+                let mut to: Vec<u32> = to.iter().copied().collect();
+                to.sort_unstable();
+                outgoing.entry(from).or_default().push((c, to));
+            }
+
+            // Every state is replaced by the smallest state of its class
+            let mut states: Vec<u32> = self.states.iter().copied().collect();
+            states.sort_unstable();
+            let mut classes: HashMap<(char, bool, Transitions), u32> = HashMap::new();
+            let mut merged: HashMap<u32, u32> = HashMap::new();
+            for state in states {
+                let Some(&c) = incoming.get(&state) else {
+                    continue;
+                };
+                let mut transitions = outgoing.remove(&state).unwrap_or_default();
+                transitions.sort_unstable();
+                let signature = (c, self.accept.contains(&state), transitions);
+                let class = *classes.entry(signature).or_insert(state);
+                if class != state {
+                    merged.insert(state, class);
+                }
+            }
+            if merged.is_empty() {
+                return self;
+            }
+
+            self.states.retain(|state| !merged.contains_key(state));
+            self.accept.retain(|state| !merged.contains_key(state));
+            self.transition_function
+                .retain(|(from, _), _| !merged.contains_key(from));
+            for to in self.transition_function.values_mut() {
+                *to = to
+                    .iter()
+                    .map(|state| *merged.get(state).unwrap_or(state))
+                    .collect();
+            }
+        }
+    }
+
+    /// Returns the NFA in the Graphviz DOT language, with states and transitions in sorted order.
+    pub fn to_dot(&self) -> String {
+        let mut states: Vec<u32> = self.states.iter().copied().collect();
+        states.sort_unstable();
+        let mut transitions: Vec<(u32, char, u32)> = self
+            .transition_function
+            .iter()
+            .flat_map(|(&(from, c), to)| to.iter().map(move |&to| (from, c, to)))
+            .collect();
+        transitions.sort_unstable();
+
+        let mut dot = String::from("digraph NFA {\n    rankdir=LR;\n    start [shape=point];\n");
+        for state in states {
+            let shape = if self.accept.contains(&state) {
+                "doublecircle"
+            } else {
+                "circle"
+            };
+            dot.push_str(&format!("    {} [shape={}];\n", state, shape));
+        }
+        dot.push_str("    start -> 0;\n");
+        for (from, c, to) in transitions {
+            // DOT strings only need quotes and backslashes escaped
+            let label = match c {
+                '"' | '\\' => format!("\\{}", c),
+                c => c.to_string(),
+            };
+            dot.push_str(&format!("    {} -> {} [label=\"{}\"];\n", from, to, label));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Simulates the NFA with the given input, first checking that every character of the input is in
+    /// the alphabet of the NFA.
+    ///
+    /// `Ok(false)` means the input was valid but did not match, whereas an error means it could never match.
+    pub fn try_run(&self, input: impl AsRef<str>) -> Result<bool, InputError> {
+        let input = input.as_ref();
+        let alphabet: HashSet<char> = self.transition_function.keys().map(|&(_, c)| c).collect();
+        if let Some((at, ch)) = input.char_indices().find(|(_, c)| !alphabet.contains(c)) {
+            return Err(InputError::CharNotInAlphabet { ch, at });
+        }
+        Ok(self.run(input))
+    }
+
+    /// Simulates the NFA with the given input, refusing inputs longer than `max_input_len` bytes and
+    /// giving up once `max_steps` steps are done, which bounds the time spent on untrusted input.
+    ///
+    /// A step is one active state reading one character, so an input of `n` characters takes at
+    /// most `n` times the number of states. The maximum length set by
+    /// [crate::builder::RegexBuilder::max_input_len] applies too.
+    pub fn try_run_bounded(
+        &self,
+        input: impl AsRef<str>,
+        max_input_len: usize,
+        max_steps: u64,
+    ) -> Result<bool, InputError> {
+        let input = input.as_ref();
+        let max = self
+            .max_input_len
+            .map_or(max_input_len, |max| max.min(max_input_len));
+        if input.len() > max {
+            return Err(InputError::TooLong {
+                len: input.len(),
+                max,
+            });
+        }
+        let mut steps: u64 = 0;
+        let mut current_states = HashSet::from([0]);
+        for (at, c) in input.char_indices() {
+            steps += current_states.len() as u64;
+            if steps > max_steps {
+                return Err(InputError::OutOfSteps { at });
+            }
+            current_states = self.step(&current_states, c);
+        }
+        Ok(!current_states.is_disjoint(&self.accept))
+    }
+
+    /// Simulates the NFA with the given input like [NFA::run], also reporting the characters that
+    /// have no transition, to help finding mismatches between the input and the pattern alphabet.
+    ///
+    /// The whole input is always read, and the maximum input length is not checked.
+    pub fn run_strict(&self, input: impl AsRef<str>) -> RunDiagnostics {
+        let input = input.as_ref();
+        let alphabet: HashSet<char> = self.transition_function.keys().map(|&(_, c)| c).collect();
+        let mut diagnostics = RunDiagnostics {
+            matched: false,
+            unknown_chars: Vec::new(),
+            stuck_at: None,
+        };
+        let mut current_states = HashSet::from([0]);
+        for (at, c) in input.char_indices() {
+            if !alphabet.contains(&c) {
+                diagnostics.unknown_chars.push((at, c));
+            }
+            if current_states.is_empty() {
+                continue;
+            }
+            current_states = self.step(&current_states, c);
+            if current_states.is_empty() {
+                diagnostics.stuck_at = Some((at, c));
+            }
+        }
+        diagnostics.matched = !current_states.is_disjoint(&self.accept);
+        diagnostics
+    }
+
+    /// Builds a NFA matching any one of the lines read from `reader`.
+    ///
+    /// Every line is taken as a literal, so no character needs escaping. A trailing `\r` is
+    /// stripped and empty lines are skipped. If there are no words the NFA matches nothing, see [NFA::never].
+    pub fn new_from_lines<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut code = 0;
+        let mut literals = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            let word = line.strip_suffix('\r').unwrap_or(&line);
+            let mut chars = word.chars().map(|c| {
+                code += 1;
+                Node::Terminal(c, code)
+            });
+            let Some(first) = chars.next() else {
+                continue;
+            };
+            literals.push(chars.fold(first, |left, right| {
+                Node::Operation(Operator::Concat, Box::new(left), Some(Box::new(right)))
+            }));
+        }
+        // A balanced alternation keeps the tree shallow for long word lists
+        let tree = balanced(Operator::Or, literals);
+        Ok(tree.map_or_else(NFA::never, |tree| crate::regex(&tree)))
+    }
+
+    /// Returns true if the NFA accepts every string over `alphabet`, the empty string included.
+    ///
+    /// This is decided by checking that the complement of the NFA over `alphabet` is empty.
+    pub fn accepts_all(&self, alphabet: &HashSet<char>) -> bool {
+        let mut alphabet: Vec<char> = alphabet.iter().copied().collect();
+        alphabet.sort_unstable();
+        DFA::from_nfa(self).complement(&alphabet).is_empty()
+    }
+
+    /// Returns a shortest string accepted by one of the two NFAs but not the other, or `None` if
+    /// they accept the same language.
+    pub fn witness_difference(&self, other: &NFA) -> Option<String> {
+        DFA::from_nfa(self).shortest_difference(&DFA::from_nfa(other))
+    }
+
+    /// Returns true if every string accepted by this NFA is also accepted by `other`.
+    ///
+    /// This is decided by checking that the intersection of this NFA with the complement of `other` is empty.
+    pub fn is_subset_of(&self, other: &NFA) -> bool {
+        let this = DFA::from_nfa(self);
+        let other = DFA::from_nfa(other);
+        let mut alphabet = this.alphabet();
+        alphabet.extend(other.alphabet());
+        alphabet.sort_unstable();
+        alphabet.dedup();
+        this.intersect(&other.complement(&alphabet)).is_empty()
+    }
+}
+
+impl NFA<u8> {
+    /// Compiles a byte pattern to its NFA over bytes, for matching binary data, see
+    /// [crate::parser::parse_bytes] for the syntax.
+    pub fn new_bytes(pattern: &[u8]) -> Result<Self, ParseError> {
+        Ok(crate::regex_over(&parse_bytes(pattern)?))
+    }
+
+    /// Simulates the NFA with the given bytes, which need not be valid UTF-8.
+    pub fn run_bytes(&self, input: &[u8]) -> bool {
+        self.run_symbols(input.iter().copied())
+    }
+}
+
+impl TryFrom<Vec<&str>> for NFA {
+    type Error = ParseError;
+
+    /// Compiles the alternation of the given literal strings, their metacharacters being escaped.
+    /// No strings give [NFA::never], and an empty string matches the empty input.
+    fn try_from(words: Vec<&str>) -> Result<Self, Self::Error> {
+        if words.is_empty() {
+            return Ok(Self::never());
+        }
+        let branches: Vec<String> = words
+            .iter()
+            .map(|word| {
+                if word.is_empty() {
+                    "()".to_string()
+                } else {
+                    escape(word)
+                }
+            })
+            .collect();
+        Self::new(&branches.join("|"))
+    }
+}
+
+/// Prints a short summary of the NFA, like `NFA { states: 3, accepting: 1 }`, for logging.
+impl fmt::Display for NFA {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "NFA {{ states: {}, accepting: {} }}",
+            self.states.len(),
+            self.accept.len()
+        )
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
+
+    /// NFA of `ab`.
+    fn ab_nfa() -> NFA {
+        crate::regex(&Node::Operation(
+            Operator::Concat,
+            Box::new(Node::Terminal('a', 1)),
+            Some(Box::new(Node::Terminal('b', 2))),
+        ))
+    }
 
     #[test]
     fn simulate_nfa_simple_test() {
@@ -100,16 +1051,604 @@ mod tests {
             ]
             .into_iter()
             .collect(),
+            ..Default::default()
         };
         assert!(nfa.run("ab"));
     }
 
+    #[test]
+    fn new_test() {
+        let nfa = NFA::new("a(b|c)*").unwrap();
+        assert!(nfa.run("abcb"));
+        assert!(!nfa.run("b"));
+        assert_eq!(
+            NFA::new("a(").unwrap_err(),
+            ParseError::UnbalancedParenthesis { at: 1 }
+        );
+    }
+
+    #[test]
+    fn run_from_test() {
+        let nfa = ab_nfa();
+        let start = HashSet::from([0]);
+        assert_eq!(nfa.run_from(&start, "ab"), HashSet::from([2]));
+        let after_a = nfa.run_from(&start, "a");
+        assert_eq!(after_a, HashSet::from([1]));
+        assert_eq!(nfa.run_from(&after_a, "b"), HashSet::from([2]));
+        assert!(nfa.run_from(&start, "ba").is_empty());
+        assert_eq!(nfa.run_from(&start, ""), start);
+    }
+
+    #[test]
+    fn run_as_ref_test() {
+        let nfa = ab_nfa();
+        let owned = String::from("ab");
+        assert!(nfa.run(&owned));
+        assert!(nfa.run(owned));
+        assert!(nfa.run(std::borrow::Cow::Borrowed("ab")));
+        assert!(!nfa.run(std::borrow::Cow::<str>::Owned("ba".to_string())));
+    }
+
+    #[test]
+    fn try_from_words_test() {
+        let nfa = NFA::try_from(vec!["cat", "dog"]).unwrap();
+        assert!(nfa.run("cat"));
+        assert!(nfa.run("dog"));
+        assert!(!nfa.run("cow"));
+        assert!(!nfa.run(""));
+        assert_eq!(nfa.find("hotdog").unwrap().range(), 3..6);
+
+        let nfa = NFA::try_from(vec!["a*", ""]).unwrap();
+        assert!(nfa.run("a*"));
+        assert!(nfa.run(""));
+        assert!(!nfa.run("aa"));
+        assert!(NFA::try_from(Vec::new()).unwrap().matches_nothing());
+    }
+
+    #[test]
+    fn new_verbose_test() {
+        let (nfa, diagnostics) = NFA::new_verbose("a(b|c)*").unwrap();
+        assert!(nfa.run("abcb"));
+        assert_eq!(diagnostics.linearized, "a1(b2|c3)*");
+        assert!(!diagnostics.nullable);
+        assert_eq!(diagnostics.prefix, vec![('a', 1)]);
+        assert_eq!(diagnostics.suffix, vec![('a', 1), ('b', 2), ('c', 3)]);
+        assert_eq!(diagnostics.factors.len(), 6);
+        assert_eq!(diagnostics.factors[0], (('a', 1), ('b', 2)));
+        assert_eq!(diagnostics.stats.states, 4);
+        assert!(NFA::new_verbose("a(").is_err());
+    }
+
+    #[test]
+    fn run_with_test() {
+        let nfa = crate::regex(&Node::from_str_literal("abc"));
+        let case_insensitive = |a: char, b: char| a.to_lowercase().eq(b.to_lowercase());
+        assert!(nfa.run_with("ABC", case_insensitive));
+        assert!(nfa.run_with("aBc", case_insensitive));
+        assert!(!nfa.run_with("ABD", case_insensitive));
+        assert!(!nfa.run_with("ABC", |a, b| a == b));
+
+        let class = NFA::new("[a-f]").unwrap();
+        assert!(class.run_with("C", case_insensitive));
+        assert!(!class.run_with("g", case_insensitive));
+        assert!(!class.run_with("G", case_insensitive));
+        let straddling = NFA::new("[Z-a]").unwrap();
+        assert!(straddling.run_with("z", case_insensitive));
+        assert!(straddling.run_with("A", case_insensitive));
+        assert!(straddling.run_with("_", case_insensitive));
+        assert!(!straddling.run_with("b", case_insensitive));
+    }
+
+    #[test]
+    fn as_literal_test() {
+        assert_eq!(ab_nfa().as_literal(), Some("ab".to_string()));
+        assert_eq!(NFA::epsilon().as_literal(), Some(String::new()));
+        assert_eq!(NFA::never().as_literal(), None);
+        assert_eq!(NFA::new("a|b").unwrap().as_literal(), None);
+        assert_eq!(NFA::new("ab*").unwrap().as_literal(), None);
+        assert_eq!(NFA::new("a(b|b)").unwrap().as_literal(), None);
+        assert_eq!(
+            NFA::new("[a]bc").unwrap().as_literal(),
+            Some("abc".to_string())
+        );
+    }
+
+    #[test]
+    fn alphabet_trace_test() {
+        let nfa = NFA::new("(c|b|a)*a").unwrap();
+        assert_eq!(nfa.alphabet(), vec!['a', 'b', 'c']);
+        assert_eq!(nfa.trace("ba"), vec![vec![0], vec![2], vec![3, 4]]);
+        assert_eq!(ab_nfa().trace("xb"), vec![vec![0]]);
+    }
+
+    #[test]
+    fn matches_nothing_test() {
+        assert!(NFA::never().matches_nothing());
+        assert!(NFA::new_from_lines("".as_bytes())
+            .unwrap()
+            .matches_nothing());
+        assert!(!NFA::epsilon().matches_nothing());
+        assert!(!ab_nfa().matches_nothing());
+    }
+
+    #[test]
+    fn epsilon_never_test() {
+        let epsilon = NFA::epsilon();
+        assert!(epsilon.run(""));
+        assert!(!epsilon.run("a"));
+        assert_eq!(epsilon.states, HashSet::from([0]));
+
+        let never = NFA::never();
+        assert!(!never.run(""));
+        assert!(!never.run("a"));
+        assert!(never.accept.is_empty());
+
+        let from_tree = crate::regex(&Node::Epsilon);
+        assert_eq!(from_tree.states, epsilon.states);
+        assert_eq!(from_tree.accept, epsilon.accept);
+        assert!(from_tree.transition_function.is_empty());
+        assert!(!NFA::new_from_lines("\n\n".as_bytes()).unwrap().run(""));
+    }
+
+    #[test]
+    fn try_run_test() {
+        let nfa = ab_nfa();
+        assert_eq!(nfa.try_run("ab"), Ok(true));
+        assert_eq!(nfa.try_run("ba"), Ok(false));
+        assert_eq!(
+            nfa.try_run("abc"),
+            Err(InputError::CharNotInAlphabet { ch: 'c', at: 2 })
+        );
+    }
+
+    #[test]
+    fn run_strict_test() {
+        let nfa = ab_nfa();
+        assert_eq!(
+            nfa.run_strict("ab"),
+            RunDiagnostics {
+                matched: true,
+                unknown_chars: vec![],
+                stuck_at: None,
+            }
+        );
+        assert_eq!(
+            nfa.run_strict("axbx"),
+            RunDiagnostics {
+                matched: false,
+                unknown_chars: vec![(1, 'x'), (3, 'x')],
+                stuck_at: Some((1, 'x')),
+            }
+        );
+        let diagnostics = nfa.run_strict("abb");
+        assert!(!diagnostics.matched);
+        assert!(diagnostics.unknown_chars.is_empty());
+        assert_eq!(diagnostics.stuck_at, Some((2, 'b')));
+    }
+
+    #[test]
+    fn run_until_test() {
+        let nfa = ab_nfa();
+        let past = Instant::now();
+        let future = Instant::now() + Duration::from_secs(60);
+        assert_eq!(nfa.run_until("ab", past), None);
+        assert_eq!(nfa.run_until("ab", future), Some(true));
+        assert_eq!(nfa.run_until("ba", future), Some(false));
+    }
+
+    #[test]
+    fn witness_difference_test() {
+        // a*b
+        let a_star_b = crate::regex(&Node::Operation(
+            Operator::Concat,
+            Box::new(Node::Operation(
+                Operator::Production,
+                Box::new(Node::Terminal('a', 1)),
+                None,
+            )),
+            Some(Box::new(Node::Terminal('b', 2))),
+        ));
+        // aa*b
+        let a_plus_b = crate::regex(&Node::Operation(
+            Operator::Concat,
+            Box::new(Node::Terminal('a', 1)),
+            Some(Box::new(Node::Operation(
+                Operator::Concat,
+                Box::new(Node::Operation(
+                    Operator::Production,
+                    Box::new(Node::Terminal('a', 2)),
+                    None,
+                )),
+                Some(Box::new(Node::Terminal('b', 3))),
+            ))),
+        ));
+        assert_eq!(
+            a_star_b.witness_difference(&a_plus_b),
+            Some("b".to_string())
+        );
+        assert_eq!(
+            a_plus_b.witness_difference(&a_star_b),
+            Some("b".to_string())
+        );
+        assert_eq!(a_star_b.witness_difference(&a_star_b), None);
+        assert_eq!(
+            ab_nfa().witness_difference(&a_plus_b),
+            Some("aab".to_string())
+        );
+    }
+
+    #[test]
+    fn accepts_all_test() {
+        // (a|b)*
+        let a_or_b_star = crate::regex(&Node::Operation(
+            Operator::Production,
+            Box::new(Node::Operation(
+                Operator::Or,
+                Box::new(Node::Terminal('a', 1)),
+                Some(Box::new(Node::Terminal('b', 2))),
+            )),
+            None,
+        ));
+        // a*
+        let a_star = crate::regex(&Node::Operation(
+            Operator::Production,
+            Box::new(Node::Terminal('a', 1)),
+            None,
+        ));
+        let alphabet = HashSet::from(['a', 'b']);
+        assert!(a_or_b_star.accepts_all(&alphabet));
+        assert!(!a_star.accepts_all(&alphabet));
+        assert!(a_star.accepts_all(&HashSet::from(['a'])));
+        assert!(!a_or_b_star.accepts_all(&HashSet::from(['a', 'b', 'c'])));
+    }
+
+    #[test]
+    fn longest_prefix_test() {
+        let nfa = ab_nfa();
+        assert_eq!(nfa.longest_prefix("abab"), Some(2));
+        assert_eq!(nfa.longest_prefix("a"), None);
+        assert_eq!(nfa.longest_prefix("ba"), None);
+    }
+
+    #[test]
+    fn shortest_prefix_test() {
+        // a(b)*
+        let nfa = crate::regex(&Node::Operation(
+            Operator::Concat,
+            Box::new(Node::Terminal('a', 1)),
+            Some(Box::new(Node::Operation(
+                Operator::Production,
+                Box::new(Node::Terminal('b', 2)),
+                None,
+            ))),
+        ));
+        assert_eq!(nfa.shortest_prefix("abb"), Some(1));
+        assert_eq!(nfa.longest_prefix("abb"), Some(3));
+        assert_eq!(nfa.shortest_prefix("b"), None);
+    }
+
+    #[test]
+    fn count_paths_test() {
+        // (a|a)*
+        let nfa = crate::regex(&Node::Operation(
+            Operator::Production,
+            Box::new(Node::Operation(
+                Operator::Or,
+                Box::new(Node::Terminal('a', 1)),
+                Some(Box::new(Node::Terminal('a', 2))),
+            )),
+            None,
+        ));
+        assert_eq!(nfa.count_paths(""), 1);
+        assert_eq!(nfa.count_paths("a"), 2);
+        assert_eq!(nfa.count_paths("aaa"), 8);
+        assert_eq!(nfa.count_paths("b"), 0);
+        assert_eq!(ab_nfa().count_paths("ab"), 1);
+    }
+
+    #[test]
+    fn ambiguity_warning_test() {
+        let star_of_or = |a, b| {
+            crate::regex(&Node::Operation(
+                Operator::Production,
+                Box::new(Node::Operation(
+                    Operator::Or,
+                    Box::new(Node::Terminal(a, 1)),
+                    Some(Box::new(Node::Terminal(b, 2))),
+                )),
+                None,
+            ))
+        };
+        let warning = star_of_or('a', 'a').ambiguity_warning().unwrap();
+        assert!(
+            warning.contains("\"a\" along 2 different paths"),
+            "{}",
+            warning
+        );
+        assert_eq!(star_of_or('a', 'b').ambiguity_warning(), None);
+        assert_eq!(ab_nfa().ambiguity_warning(), None);
+    }
+
+    #[test]
+    fn char_match_ranges_test() {
+        let nfa = ab_nfa();
+        assert_eq!(nfa.char_match_ranges("ab"), Some(vec![(0, 1), (1, 2)]));
+        assert_eq!(nfa.char_match_ranges("ba"), None);
+    }
+
+    #[test]
+    fn run_utf16_test() {
+        let nfa = crate::regex(&Node::Operation(
+            Operator::Concat,
+            Box::new(Node::Terminal('😀', 1)),
+            Some(Box::new(Node::Terminal('a', 2))),
+        ));
+        let input: Vec<u16> = "😀a".encode_utf16().collect();
+        assert_eq!(input.len(), 3);
+        assert!(nfa.run_utf16(&input));
+        assert!(!nfa.run_utf16(&input[1..]));
+        assert!(!nfa.run_utf16(&[0xD83D, 'a' as u16]));
+    }
+
+    #[test]
+    fn display_test() {
+        assert_eq!(ab_nfa().to_string(), "NFA { states: 3, accepting: 1 }");
+        assert_eq!(NFA::never().to_string(), "NFA { states: 1, accepting: 0 }");
+    }
+
+    #[test]
+    fn accept_info_test() {
+        assert_eq!(ab_nfa().accept_info(), vec![(2, 'b')]);
+        assert_eq!(NFA::epsilon().accept_info(), vec![]);
+    }
+
+    #[test]
+    fn to_dot_test() {
+        assert_eq!(
+            ab_nfa().to_dot(),
+            "digraph NFA {
+    rankdir=LR;
+    start [shape=point];
+    0 [shape=circle];
+    1 [shape=circle];
+    2 [shape=doublecircle];
+    start -> 0;
+    0 -> 1 [label=\"a\"];
+    1 -> 2 [label=\"b\"];
+}
+"
+        );
+        let dot = NFA::new(r#"é"\\"#).unwrap().to_dot();
+        assert!(dot.contains("0 -> 1 [label=\"é\"];"), "{}", dot);
+        assert!(dot.contains(r#"1 -> 2 [label="\""];"#), "{}", dot);
+        assert!(dot.contains(r#"2 -> 3 [label="\\"];"#), "{}", dot);
+    }
+
+    #[test]
+    fn is_viable_prefix_test() {
+        // aaab
+        let nfa = crate::regex(&Node::from_str_literal("aaab"));
+        assert!(nfa.is_viable_prefix(""));
+        assert!(nfa.is_viable_prefix("aa"));
+        assert!(nfa.is_viable_prefix("aaab"));
+        assert!(!nfa.is_viable_prefix("ax"));
+        assert!(!nfa.is_viable_prefix("aaabb"));
+    }
+
+    #[test]
+    fn match_len_bounds_test() {
+        let nfa = NFA::new("a{2,4}").unwrap();
+        assert_eq!(nfa.match_len_bounds(), (2, Some(4)));
+        assert_eq!(NFA::new("a*").unwrap().match_len_bounds(), (0, None));
+        assert_eq!(NFA::new("ab(c|d)*e").unwrap().match_len_bounds(), (3, None));
+        assert_eq!(NFA::epsilon().match_len_bounds(), (0, Some(0)));
+        assert_eq!(NFA::never().match_len_bounds(), (0, Some(0)));
+
+        let nfa = NFA::new("abc(d|e)").unwrap();
+        assert_eq!(nfa.min_len, 4);
+        assert!(!nfa.run("abc"));
+        assert!(nfa.run("abcd"));
+        assert_eq!(nfa.find("xabcex").unwrap().range(), 1..5);
+        assert_eq!(nfa.find("abc"), None);
+    }
+
+    #[test]
+    fn try_set_to_nfa_test() {
+        let single = HashSet::from([SetTerminal::SingleElement('a', 1)]);
+        let double = HashSet::from([SetTerminal::DoubleElement('a', 1, 'a', 1)]);
+        let nfa = NFA::try_set_to_nfa(&single, &single, &double).unwrap();
+        assert!(nfa.run("aaa"));
+        assert_eq!(
+            NFA::try_set_to_nfa(&double, &single, &double).unwrap_err(),
+            CompileError::MalformedSets
+        );
+        assert_eq!(
+            NFA::try_set_to_nfa(&single, &double, &double).unwrap_err(),
+            CompileError::MalformedSets
+        );
+        assert_eq!(
+            NFA::try_set_to_nfa(&single, &single, &single).unwrap_err(),
+            CompileError::MalformedSets
+        );
+    }
+
+    #[test]
+    fn merge_equivalent_states_test() {
+        let nfa = NFA::new("(ab|ab|ab)c|a*").unwrap();
+        let merged = NFA::new("(ab|ab|ab)c|a*")
+            .unwrap()
+            .merge_equivalent_states();
+        // The three `a` of the alternation and their `b` are merged, the `a` of `a*` loops so it is kept
+        assert_eq!(nfa.stats().states, 9);
+        assert_eq!(merged.stats().states, 5);
+        for input in ["abc", "ab", "", "aaa", "abcc", "aabc", "c"] {
+            assert_eq!(merged.run(input), nfa.run(input), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn trim_test() {
+        // ab, with the unreachable state 3 and the dead state 4
+        let nfa = NFA {
+            states: HashSet::from([0, 1, 2, 3, 4]),
+            accept: HashSet::from([2]),
+            transition_function: HashMap::from([
+                ((0, 'a'), HashSet::from([1, 4])),
+                ((1, 'b'), HashSet::from([2])),
+                ((3, 'a'), HashSet::from([2])),
+                ((4, 'c'), HashSet::from([4])),
+            ]),
+            ..Default::default()
+        };
+        let trimmed = nfa.trim();
+        assert_eq!(trimmed.states, HashSet::from([0, 1, 2]));
+        assert!(!trimmed.transition_function.contains_key(&(4, 'c')));
+        for input in ["ab", "a", "ac", "acc", "", "b"] {
+            assert_eq!(trimmed.run(input), nfa.run(input), "input {:?}", input);
+        }
+    }
+
+    #[test]
+    fn run_symbols_test() {
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        enum Event {
+            Open,
+            Data,
+            Close,
+        }
+
+        // Open Data* Close
+        let tree = Node::Operation(
+            Operator::Concat,
+            Box::new(Node::Operation(
+                Operator::Concat,
+                Box::new(Node::Terminal(Event::Open, 1)),
+                Some(Box::new(Node::Operation(
+                    Operator::Production,
+                    Box::new(Node::Terminal(Event::Data, 2)),
+                    None,
+                ))),
+            )),
+            Some(Box::new(Node::Terminal(Event::Close, 3))),
+        );
+        let nfa = crate::regex_over(&tree);
+        assert!(nfa.run_symbols([Event::Open, Event::Close]));
+        assert!(nfa.run_symbols([Event::Open, Event::Data, Event::Data, Event::Close]));
+        assert!(!nfa.run_symbols([Event::Open, Event::Data]));
+        assert!(!nfa.run_symbols([Event::Data, Event::Close]));
+    }
+
     #[test]
     fn set_to_nfa_simple_test() {
-        let prefix_set = vec![SetTerminal::SingleElement('a', 1)].into_iter().collect();
-        let suffix_set = vec![SetTerminal::SingleElement('b', 2)].into_iter().collect();
-        let factors_set = vec![SetTerminal::DoubleElement('a', 1, 'b', 2)].into_iter().collect();
+        let prefix_set = vec![SetTerminal::SingleElement('a', 1)]
+            .into_iter()
+            .collect();
+        let suffix_set = vec![SetTerminal::SingleElement('b', 2)]
+            .into_iter()
+            .collect();
+        let factors_set = vec![SetTerminal::DoubleElement('a', 1, 'b', 2)]
+            .into_iter()
+            .collect();
         let nfa = NFA::set_to_nfa(&prefix_set, &suffix_set, &factors_set);
         assert!(nfa.run("ab"));
     }
+
+    #[test]
+    fn new_from_lines_test() {
+        let nfa = NFA::new_from_lines("cat\ncow\r\n\ndog\n".as_bytes()).unwrap();
+        assert!(nfa.run("cat"));
+        assert!(nfa.run("dog"));
+        assert!(nfa.run("cow"));
+        assert!(!nfa.run(""));
+        assert!(!nfa.run("at"));
+        assert!(!nfa.run("catdog"));
+
+        let words: String = (0..20_000).map(|i| format!("w{}\n", i)).collect();
+        let nfa = NFA::new_from_lines(words.as_bytes()).unwrap();
+        assert!(nfa.run("w0"));
+        assert!(nfa.run("w19999"));
+        assert!(!nfa.run("w20000"));
+    }
+
+    #[test]
+    fn determinism_report_test() {
+        let nfa = NFA::new("ab|ac|ad|b").unwrap();
+        assert_eq!(nfa.determinism_report(), vec![(0, 'a', 3)]);
+        let nfa = NFA::new("(a|ab)*").unwrap();
+        assert_eq!(
+            nfa.determinism_report(),
+            vec![(0, 'a', 2), (1, 'a', 2), (3, 'a', 2)]
+        );
+        assert!(NFA::new("abc|bcd").unwrap().determinism_report().is_empty());
+    }
+
+    #[test]
+    fn try_run_bounded_test() {
+        let nfa = NFA::new("(a|ab)*b").unwrap();
+        assert_eq!(nfa.try_run_bounded("abab", 4, 100), Ok(true));
+        assert_eq!(nfa.try_run_bounded("aba", 4, 100), Ok(false));
+        assert_eq!(
+            nfa.try_run_bounded("ababa", 4, 100),
+            Err(InputError::TooLong { len: 5, max: 4 })
+        );
+        // After the first `a`, both the states of `a` and `ab` are active
+        assert_eq!(nfa.try_run_bounded("aab", 100, 5), Ok(true));
+        assert_eq!(
+            nfa.try_run_bounded("aaab", 100, 5),
+            Err(InputError::OutOfSteps { at: 3 })
+        );
+
+        let limited = crate::builder::RegexBuilder::new()
+            .max_input_len(2)
+            .build(parse("a*").unwrap())
+            .unwrap();
+        assert_eq!(
+            limited.try_run_bounded("aaa", 10, 100),
+            Err(InputError::TooLong { len: 3, max: 2 })
+        );
+    }
+
+    #[test]
+    fn new_bytes_test() {
+        // A frame starting with 0xff 0x00, followed by any number of 0x80 or 0x81 and a newline
+        let nfa = NFA::new_bytes(b"\xff\x00[\x80\x81]*\n").unwrap();
+        assert!(nfa.run_bytes(b"\xff\x00\n"));
+        assert!(nfa.run_bytes(b"\xff\x00\x81\x80\x80\n"));
+        assert!(!nfa.run_bytes(b"\xff\x00\x82\n"));
+        assert!(!nfa.run_bytes(b"\xff\n"));
+
+        let nfa = NFA::new_bytes(b"(?i:ab\xe9)").unwrap();
+        assert!(nfa.run_bytes(b"aB\xe9"));
+        assert!(!nfa.run_bytes(b"ab\xc9"));
+        assert_eq!(
+            NFA::new_bytes(b"\xff)").unwrap_err(),
+            ParseError::UnbalancedParenthesis { at: 1 }
+        );
+    }
+
+    #[test]
+    fn is_subset_of_test() {
+        let ab = ab_nfa();
+        // (a|b)(a|b)*, i.e. (a|b)+
+        let a_or_b_plus = crate::regex(&Node::Operation(
+            Operator::Concat,
+            Box::new(Node::Operation(
+                Operator::Or,
+                Box::new(Node::Terminal('a', 1)),
+                Some(Box::new(Node::Terminal('b', 2))),
+            )),
+            Some(Box::new(Node::Operation(
+                Operator::Production,
+                Box::new(Node::Operation(
+                    Operator::Or,
+                    Box::new(Node::Terminal('a', 3)),
+                    Some(Box::new(Node::Terminal('b', 4))),
+                )),
+                None,
+            ))),
+        ));
+        assert!(ab.is_subset_of(&a_or_b_plus));
+        assert!(!a_or_b_plus.is_subset_of(&ab));
+        assert!(ab.is_subset_of(&ab));
+    }
 }
@@ -0,0 +1,906 @@
+//! Contains the parser of pattern strings like `"(a|b)*c"` into regular expression trees.
+//!
+//! The syntax has literal characters, concatenation by juxtaposition, alternation with `|`, the
+//! star `*`, the plus `+`, the question mark `?` and grouping with parentheses. A backslash makes
+//! the next character literal, so `\*` matches a star. The empty pattern and the empty group `()` match the empty string.
+//! A counted repetition `{n}`, `{n,}` or `{n,m}` repeats its operand at least `n` and at most `m`
+//! times, and is expanded to concatenations of the operand, starred or optional.
+//!
+//! A class like `[a-cx]` matches any one of its characters and ranges. Inside a class `-` is
+//! literal at the start or the end, `]` is literal first, and both can be escaped anywhere. A `]`
+//! outside a class must be escaped.
+//! A group starting with flags, like `(?i:ab)`, applies them to its contents only. The flag `i`
+//! makes letters match both cases, and `-i` turns it back off, as in `(?i:a(?-i:b))`.
+//! Negated classes like `[^a]` are refused with [ParseError::NegatedClass], as the construction
+//! needs the characters to be listed.
+
+use crate::translation::node::{balanced, Node};
+use crate::translation::operator::Operator;
+use std::fmt;
+use std::iter::Peekable;
+
+/// The `ParseError` enum represents the reasons a pattern string can be refused by [parse].
+///
+/// Every variant holds the byte offset in the pattern where the problem was found.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// A `(` is never closed, or a `)` closes no group.
+    UnbalancedParenthesis { at: usize },
+    /// An operator is missing its operand, like the star of `*a` or the `|` of `a||b`.
+    DanglingOperator { op: char, at: usize },
+    /// The pattern ends with a backslash escaping nothing.
+    TrailingEscape { at: usize },
+    /// A `[` is never closed, or a `]` closes no class.
+    UnbalancedClass { at: usize },
+    /// A range of a class ends before it starts, like `z-a`.
+    InvalidRange { at: usize },
+    /// A class is negated, which the construction does not support.
+    NegatedClass { at: usize },
+    /// A counted repetition is malformed or its bounds are reversed, like `a{2,1}`.
+    InvalidRepetition { at: usize },
+    /// The counted repetitions would expand to more nodes than allowed, like `a{1,1000000}`, see
+    /// [MAX_REPETITION_NODES]. The offset is the one of the repetition passing the limit.
+    RepetitionTooLarge { at: usize },
+    /// A flag group uses a flag other than `i`, like the `x` of `(?x:a)`.
+    UnknownFlag { flag: char, at: usize },
+    /// A group is nested in more than [MAX_NESTING_DEPTH] groups.
+    NestingTooDeep { at: usize },
+    /// A class has more than [MAX_CLASS_SIZE] characters.
+    ClassTooLarge { at: usize },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnbalancedParenthesis { at } => {
+                write!(f, "unbalanced parenthesis at byte {}", at)
+            }
+            ParseError::DanglingOperator { op, at } => {
+                write!(f, "operator {:?} at byte {} is missing its operand", op, at)
+            }
+            ParseError::TrailingEscape { at } => {
+                write!(f, "the escape at byte {} escapes nothing", at)
+            }
+            ParseError::UnbalancedClass { at } => {
+                write!(f, "unbalanced class bracket at byte {}", at)
+            }
+            ParseError::InvalidRange { at } => {
+                write!(f, "the range at byte {} ends before it starts", at)
+            }
+            ParseError::NegatedClass { at } => {
+                write!(f, "the negated class at byte {} is not supported", at)
+            }
+            ParseError::InvalidRepetition { at } => {
+                write!(f, "invalid repetition at byte {}", at)
+            }
+            ParseError::RepetitionTooLarge { at } => {
+                write!(f, "the repetition at byte {} is too large", at)
+            }
+            ParseError::UnknownFlag { flag, at } => {
+                write!(f, "unknown flag {:?} at byte {}", flag, at)
+            }
+            ParseError::NestingTooDeep { at } => {
+                write!(f, "the group at byte {} is nested too deeply", at)
+            }
+            ParseError::ClassTooLarge { at } => {
+                write!(f, "the class at byte {} has too many characters", at)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Default maximum number of nodes the counted repetitions of a pattern can expand to, in total.
+/// Larger repetitions fail with [ParseError::RepetitionTooLarge] before being expanded, as their
+/// trees and sets would exhaust the memory. Many repetitions each below the limit, like
+/// `a{30000}b{30000}`, fail as well once their sum exceeds it.
+pub const MAX_REPETITION_NODES: usize = 100_000;
+
+/// Maximum number of groups a group can be nested in. Deeper groups fail with
+/// [ParseError::NestingTooDeep], as the parser recurses once per group.
+pub const MAX_NESTING_DEPTH: usize = 256;
+
+/// Maximum number of characters of a class. Larger classes, like `[\0-\u{10FFFF}]`, fail with
+/// [ParseError::ClassTooLarge], as every character becomes a terminal of the tree.
+pub const MAX_CLASS_SIZE: usize = 4096;
+
+/// Parses a pattern string to a regular expression tree, its terminals numbered from `1`.
+pub fn parse(pattern: &str) -> Result<Node, ParseError> {
+    parse_with_limit(pattern, MAX_REPETITION_NODES)
+}
+
+/// Parses a pattern string like [parse], with another maximum number of nodes for its counted repetitions.
+pub(crate) fn parse_with_limit(
+    pattern: &str,
+    max_repetition_nodes: usize,
+) -> Result<Node, ParseError> {
+    Parser {
+        chars: pattern.char_indices().peekable(),
+        ignore_case: false,
+        ascii_only: false,
+        max_repetition_nodes,
+        repetition_nodes: 0,
+        depth: 0,
+    }
+    .parse()
+}
+
+/// Parses a byte pattern to a regular expression tree over bytes, its terminals numbered from `1`.
+///
+/// The syntax is the one of [parse], every byte being a character. The metacharacters are ASCII,
+/// so the bytes from `0x80` are always literal and can match input that is not valid UTF-8. The
+/// flag `i` only folds ASCII letters.
+pub fn parse_bytes(pattern: &[u8]) -> Result<Node<u8>, ParseError> {
+    let tree = Parser {
+        chars: pattern
+            .iter()
+            .map(|&b| char::from(b))
+            .enumerate()
+            .peekable(),
+        ignore_case: false,
+        ascii_only: true,
+        max_repetition_nodes: MAX_REPETITION_NODES,
+        repetition_nodes: 0,
+        depth: 0,
+    }
+    .parse()?;
+    Ok(tree.fold(
+        &mut |leaf| match *leaf {
+            // Every character comes from a byte, or from folding an ASCII letter
+            Node::Terminal(c, code) => Node::Terminal(c as u8, code),
+            _ => Node::Epsilon,
+        },
+        &mut |op, left, right| Node::Operation(*op, Box::new(left), right.map(Box::new)),
+    ))
+}
+
+/// Characters having a meaning in a pattern, see [escape].
+const METACHARACTERS: &[char] = &['*', '+', '?', '|', '(', ')', '.', '\\', '[', ']', '{', '}'];
+
+/// Returns the pattern matching exactly the given text, its metacharacters escaped with a backslash.
+///
+/// This lets arbitrary text be embedded in a larger pattern, like `format!("{}|b*", escape(text))`.
+pub fn escape(literal: &str) -> String {
+    let mut escaped = String::with_capacity(literal.len());
+    for c in literal.chars() {
+        if METACHARACTERS.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+impl TryFrom<&str> for Node {
+    type Error = ParseError;
+
+    /// Parses the pattern string, see [parse].
+    fn try_from(pattern: &str) -> Result<Self, Self::Error> {
+        parse(pattern)
+    }
+}
+
+/// The characters of a class, as inclusive ranges.
+#[derive(Debug, PartialEq, Eq)]
+struct CharClass {
+    negated: bool,
+    ranges: Vec<(char, char)>,
+}
+
+impl CharClass {
+    /// Returns true if the class matches the character.
+    #[cfg(test)]
+    fn contains(&self, c: char) -> bool {
+        self.ranges.iter().any(|&(low, high)| low <= c && c <= high) != self.negated
+    }
+
+    /// Returns the characters of the class. `at` is the byte offset of the class.
+    fn chars(&self, at: usize) -> Result<Vec<char>, ParseError> {
+        if self.negated {
+            return Err(ParseError::NegatedClass { at });
+        }
+        // Counted without listing them, ranges spanning the surrogates counting them too
+        let size: usize = self
+            .ranges
+            .iter()
+            .map(|&(low, high)| (high as usize - low as usize) + 1)
+            .sum();
+        if size > MAX_CLASS_SIZE {
+            return Err(ParseError::ClassTooLarge { at });
+        }
+        Ok(self
+            .ranges
+            .iter()
+            .flat_map(|&(low, high)| low..=high)
+            .collect())
+    }
+}
+
+/// Returns the character with its single character lowercase and uppercase forms, or only the
+/// character if `ascii_only` is set and it is not ASCII.
+fn case_variants(c: char, ascii_only: bool) -> Vec<char> {
+    if ascii_only && !c.is_ascii() {
+        return vec![c];
+    }
+    let lower: Vec<char> = c.to_lowercase().collect();
+    let upper: Vec<char> = c.to_uppercase().collect();
+    let mut variants = vec![c];
+    for mapped in [lower, upper] {
+        if let [variant] = mapped[..] {
+            variants.push(variant);
+        }
+    }
+    variants
+}
+
+/// Returns the alternation of the terminals of the characters, sorted and without duplicates.
+///
+/// The alternation is balanced, so the depth of the tree only grows with the logarithm of the
+/// number of characters.
+fn alternation_of(mut chars: Vec<char>) -> Node {
+    chars.sort_unstable();
+    chars.dedup();
+    let terminals = chars.into_iter().map(|c| Node::Terminal(c, 0)).collect();
+    balanced(Operator::Or, terminals).expect("an alternation has at least one character")
+}
+
+/// Returns an upper bound of the number of nodes of [repeat_node], without expanding it.
+///
+/// Every copy of the tree comes with at most a star or question mark and a concatenation.
+fn repetition_size(tree: &Node, min: u32, max: Option<u32>) -> usize {
+    let size = tree.fold(&mut |_| 1usize, &mut |_, left, right| {
+        1 + left + right.unwrap_or(0)
+    });
+    let copies = max.unwrap_or(min.saturating_add(1)) as usize;
+    (size + 2).saturating_mul(copies)
+}
+
+/// Returns the concatenation of `min` copies of the tree, followed by the tree starred if `max` is
+/// `None`, or by `max - min` optional copies. No copies give [Node::Epsilon].
+fn repeat_node(tree: Node, min: u32, max: Option<u32>) -> Node {
+    let optional = || Node::Operation(Operator::Question, Box::new(tree.clone()), None);
+    let mut copies: Vec<Node> = (0..min).map(|_| tree.clone()).collect();
+    match max {
+        None => copies.push(Node::Operation(
+            Operator::Production,
+            Box::new(tree.clone()),
+            None,
+        )),
+        Some(max) => copies.extend((min..max).map(|_| optional())),
+    }
+    copies
+        .into_iter()
+        .reduce(|left, right| {
+            Node::Operation(Operator::Concat, Box::new(left), Some(Box::new(right)))
+        })
+        .unwrap_or(Node::Epsilon)
+}
+
+/// Recursive descent parser, one method per precedence level.
+///
+/// The characters come with their byte offset in the pattern.
+struct Parser<I: Iterator<Item = (usize, char)>> {
+    chars: Peekable<I>,
+    /// Whether the `i` flag is set in the current group.
+    ignore_case: bool,
+    /// Whether the `i` flag only folds ASCII letters.
+    ascii_only: bool,
+    /// The maximum number of nodes the counted repetitions can expand to, in total.
+    max_repetition_nodes: usize,
+    /// The number of nodes the counted repetitions parsed so far expand to.
+    repetition_nodes: usize,
+    /// The number of groups the parser is in.
+    depth: usize,
+}
+
+impl<I: Iterator<Item = (usize, char)> + Clone> Parser<I> {
+    /// Parses the whole pattern.
+    fn parse(mut self) -> Result<Node, ParseError> {
+        let mut tree = self.alternation()?;
+        if let Some((at, _)) = self.chars.next() {
+            // The alternation only stops early on a closing parenthesis
+            return Err(ParseError::UnbalancedParenthesis { at });
+        }
+        tree.renumber();
+        Ok(tree)
+    }
+
+    /// Parses alternatives separated by `|`, up to the end of the pattern or a closing parenthesis.
+    fn alternation(&mut self) -> Result<Node, ParseError> {
+        let first = self.concatenation()?;
+        let mut tree = match self.chars.peek() {
+            Some(&(at, '|')) => first.ok_or(ParseError::DanglingOperator { op: '|', at })?,
+            _ => return Ok(first.unwrap_or(Node::Epsilon)),
+        };
+        while let Some(&(at, '|')) = self.chars.peek() {
+            self.chars.next();
+            let right = self
+                .concatenation()?
+                .ok_or(ParseError::DanglingOperator { op: '|', at })?;
+            tree = Node::Operation(Operator::Or, Box::new(tree), Some(Box::new(right)));
+        }
+        Ok(tree)
+    }
+
+    /// Parses a sequence of atoms and their postfix operators, returning `None` if it is empty.
+    fn concatenation(&mut self) -> Result<Option<Node>, ParseError> {
+        let mut tree: Option<Node> = None;
+        while let Some(&(_, c)) = self.chars.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let operand = self.postfix()?;
+            tree = Some(match tree {
+                Some(left) => {
+                    Node::Operation(Operator::Concat, Box::new(left), Some(Box::new(operand)))
+                }
+                None => operand,
+            });
+        }
+        Ok(tree)
+    }
+
+    /// Parses an atom followed by any number of postfix operators, `*`, `+`, `?` or a counted
+    /// repetition, each applying to everything before it.
+    fn postfix(&mut self) -> Result<Node, ParseError> {
+        let mut tree = self.atom()?;
+        while let Some(&(at, c)) = self.chars.peek() {
+            let op = match c {
+                '*' => Operator::Production,
+                '+' => Operator::Plus,
+                '?' => Operator::Question,
+                '{' => {
+                    self.chars.next();
+                    let (min, max) = self.repetition(at)?;
+                    self.repetition_nodes = self
+                        .repetition_nodes
+                        .saturating_add(repetition_size(&tree, min, max));
+                    if self.repetition_nodes > self.max_repetition_nodes {
+                        return Err(ParseError::RepetitionTooLarge { at });
+                    }
+                    tree = repeat_node(tree, min, max);
+                    continue;
+                }
+                _ => break,
+            };
+            self.chars.next();
+            tree = Node::Operation(op, Box::new(tree), None);
+        }
+        Ok(tree)
+    }
+
+    /// Parses the bounds of a counted repetition up to its `}`, the `{` at byte `at` being
+    /// already read. An unbounded maximum is `None`.
+    fn repetition(&mut self, at: usize) -> Result<(u32, Option<u32>), ParseError> {
+        let min = self.number().ok_or(ParseError::InvalidRepetition { at })?;
+        let max = match self.chars.next() {
+            Some((_, '}')) => return Ok((min, Some(min))),
+            Some((_, ',')) => self.number(),
+            _ => return Err(ParseError::InvalidRepetition { at }),
+        };
+        match self.chars.next() {
+            Some((_, '}')) if max.is_none_or(|max| min <= max) => Ok((min, max)),
+            _ => Err(ParseError::InvalidRepetition { at }),
+        }
+    }
+
+    /// Reads a decimal number, returning `None` if there is none or it does not fit in a `u32`.
+    fn number(&mut self) -> Option<u32> {
+        let mut number: Option<u32> = None;
+        while let Some(&(_, c)) = self.chars.peek() {
+            let Some(digit) = c.to_digit(10) else {
+                break;
+            };
+            self.chars.next();
+            number = Some(
+                number
+                    .unwrap_or(0)
+                    .checked_mul(10)
+                    .and_then(|n| n.checked_add(digit))
+                    .unwrap_or(u32::MAX),
+            );
+        }
+        number.filter(|&n| n != u32::MAX)
+    }
+
+    /// Parses the rest of a class, the `[` at byte `at` being already read.
+    fn class(&mut self, at: usize) -> Result<CharClass, ParseError> {
+        let mut class = CharClass {
+            negated: false,
+            ranges: Vec::new(),
+        };
+        if let Some(&(_, '^')) = self.chars.peek() {
+            self.chars.next();
+            class.negated = true;
+        }
+        let mut first = true;
+        loop {
+            let (range_at, low) = match self.chars.next() {
+                Some((_, ']')) if !first => return Ok(class),
+                Some((escape_at, '\\')) => (escape_at, self.class_escape(at)?),
+                Some(member) => member,
+                None => return Err(ParseError::UnbalancedClass { at }),
+            };
+            first = false;
+            let mut high = low;
+            if let Some(&(_, '-')) = self.chars.peek() {
+                let mut lookahead = self.chars.clone();
+                lookahead.next();
+                // A `-` before the closing `]` is literal
+                if !matches!(lookahead.peek(), Some(&(_, ']')) | None) {
+                    self.chars.next();
+                    high = match self.chars.next() {
+                        Some((_, '\\')) => self.class_escape(at)?,
+                        Some((_, c)) => c,
+                        None => return Err(ParseError::UnbalancedClass { at }),
+                    };
+                    if high < low {
+                        return Err(ParseError::InvalidRange { at: range_at });
+                    }
+                }
+            }
+            class.ranges.push((low, high));
+        }
+    }
+
+    /// Parses the flags of a group up to their `:`, the `(?` at byte `at` being already read, and
+    /// returns the value of `i` inside the group.
+    fn flags(&mut self, at: usize) -> Result<bool, ParseError> {
+        let mut ignore_case = self.ignore_case;
+        let mut enable = true;
+        loop {
+            match self.chars.next() {
+                Some((_, ':')) => return Ok(ignore_case),
+                Some((_, '-')) if enable => enable = false,
+                Some((_, 'i')) => ignore_case = enable,
+                Some((flag_at, flag)) => return Err(ParseError::UnknownFlag { flag, at: flag_at }),
+                None => return Err(ParseError::UnbalancedParenthesis { at }),
+            }
+        }
+    }
+
+    /// Returns the terminal of a literal character, or the alternation of its cases if `i` is set.
+    fn literal(&self, c: char) -> Node {
+        if self.ignore_case {
+            alternation_of(case_variants(c, self.ascii_only))
+        } else {
+            Node::Terminal(c, 0)
+        }
+    }
+
+    /// Returns the alternation of the characters of a class, with both cases of its letters if
+    /// `i` is set.
+    fn class_node(&self, chars: Vec<char>) -> Node {
+        if self.ignore_case {
+            let ascii_only = self.ascii_only;
+            alternation_of(
+                chars
+                    .into_iter()
+                    .flat_map(|c| case_variants(c, ascii_only))
+                    .collect(),
+            )
+        } else {
+            alternation_of(chars)
+        }
+    }
+
+    /// Reads the character escaped inside the class at byte `at`.
+    fn class_escape(&mut self, at: usize) -> Result<char, ParseError> {
+        self.chars
+            .next()
+            .map(|(_, c)| c)
+            .ok_or(ParseError::UnbalancedClass { at })
+    }
+
+    /// Parses a literal character, an escaped character, a class or a group.
+    fn atom(&mut self) -> Result<Node, ParseError> {
+        let (at, c) = self
+            .chars
+            .next()
+            .expect("atom called at the end of the pattern");
+        match c {
+            '(' => {
+                if self.depth == MAX_NESTING_DEPTH {
+                    return Err(ParseError::NestingTooDeep { at });
+                }
+                let outer_ignore_case = self.ignore_case;
+                if let Some(&(_, '?')) = self.chars.peek() {
+                    self.chars.next();
+                    self.ignore_case = self.flags(at)?;
+                }
+                self.depth += 1;
+                let group = self.alternation();
+                self.depth -= 1;
+                self.ignore_case = outer_ignore_case;
+                let group = group?;
+                match self.chars.next() {
+                    Some((_, ')')) => Ok(group),
+                    _ => Err(ParseError::UnbalancedParenthesis { at }),
+                }
+            }
+            '[' => {
+                let chars = self.class(at)?.chars(at)?;
+                Ok(self.class_node(chars))
+            }
+            '*' | '+' | '?' | '{' => Err(ParseError::DanglingOperator { op: c, at }),
+            ']' => Err(ParseError::UnbalancedClass { at }),
+            '\\' => match self.chars.next() {
+                Some((_, escaped)) => Ok(self.literal(escaped)),
+                None => Err(ParseError::TrailingEscape { at }),
+            },
+            _ => Ok(self.literal(c)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn t(symbol: char, code: u32) -> Node {
+        Node::Terminal(symbol, code)
+    }
+
+    #[test]
+    fn plus_question_test() {
+        assert_eq!(
+            parse("a+b"),
+            Ok(Node::Operation(
+                Operator::Concat,
+                Box::new(Node::Operation(Operator::Plus, Box::new(t('a', 1)), None)),
+                Some(Box::new(t('b', 2))),
+            ))
+        );
+        let nfa = crate::nfa::NFA::new("a+b").unwrap();
+        assert!(nfa.run("ab"));
+        assert!(nfa.run("aaab"));
+        assert!(!nfa.run("b"));
+
+        let nfa = crate::nfa::NFA::new("a?b").unwrap();
+        assert!(nfa.run("ab"));
+        assert!(nfa.run("b"));
+        assert!(!nfa.run("aab"));
+
+        // The postfix operators bind tighter than the concatenation, and can be stacked
+        let nfa = crate::nfa::NFA::new("(ab)+c?|d*?").unwrap();
+        assert!(nfa.run("ababc"));
+        assert!(nfa.run("ab"));
+        assert!(nfa.run(""));
+        assert!(nfa.run("ddd"));
+        assert!(!nfa.run("c"));
+        assert_eq!(
+            parse("+a"),
+            Err(ParseError::DanglingOperator { op: '+', at: 0 })
+        );
+        assert_eq!(
+            parse("a|?"),
+            Err(ParseError::DanglingOperator { op: '?', at: 2 })
+        );
+    }
+
+    #[test]
+    fn postfix_group_test() {
+        // The operators apply to the whole group, unlike in `ab*`
+        let group = crate::nfa::NFA::new("(ab)*").unwrap();
+        let last = crate::nfa::NFA::new("ab*").unwrap();
+        assert!(group.run("abab"));
+        assert!(group.run(""));
+        assert!(!group.run("aba"));
+        assert!(!group.run("abb"));
+        assert!(last.run("abb"));
+        assert!(!last.run("abab"));
+
+        let plus = crate::nfa::NFA::new("(ab)+").unwrap();
+        assert!(plus.run("abab"));
+        assert!(!plus.run(""));
+        let question = crate::nfa::NFA::new("x(ab)?").unwrap();
+        assert!(question.run("x"));
+        assert!(question.run("xab"));
+        assert!(!question.run("xa"));
+    }
+
+    #[test]
+    fn adversarial_patterns_test() {
+        for pattern in [
+            "(", ")", "*", "+a", "a||b", "\\", "[", "[]", "[z-a]", "[^a]", "((a)", "a)(", "(|)*?\\",
+        ] {
+            assert!(parse(pattern).is_err(), "{:?} parsed", pattern);
+        }
+    }
+
+    #[test]
+    fn repetition_test() {
+        let nfa = crate::nfa::NFA::new("[a-z]+").unwrap();
+        assert!(nfa.run("abc"));
+        assert!(!nfa.run(""));
+        assert!(!nfa.run("aBc"));
+        let nfa = crate::nfa::NFA::new("(ab)?").unwrap();
+        assert!(nfa.run(""));
+        assert!(nfa.run("ab"));
+        assert!(!nfa.run("abab"));
+
+        let nfa = crate::nfa::NFA::new("[a-c]{2}").unwrap();
+        assert!(nfa.run("ca"));
+        assert!(!nfa.run("c"));
+        assert!(!nfa.run("cab"));
+        let nfa = crate::nfa::NFA::new("(ab){1,3}c").unwrap();
+        for (input, expected) in [("c", false), ("abc", true), ("ababab", false)] {
+            assert_eq!(nfa.run(input), expected, "{:?}", input);
+        }
+        assert!(nfa.run("abababc"));
+        assert!(!nfa.run("ababababc"));
+        let nfa = crate::nfa::NFA::new("a{2,}|b{0}").unwrap();
+        assert!(nfa.run("aaaa"));
+        assert!(nfa.run(""));
+        assert!(!nfa.run("a"));
+
+        for (pattern, at) in [
+            ("a{", 1),
+            ("a{,2}", 1),
+            ("a{2,1}", 1),
+            ("ba{1x}", 2),
+            ("a{99999999999}", 1),
+        ] {
+            assert_eq!(
+                parse(pattern),
+                Err(ParseError::InvalidRepetition { at }),
+                "{:?}",
+                pattern
+            );
+        }
+        assert_eq!(
+            parse("{2}"),
+            Err(ParseError::DanglingOperator { op: '{', at: 0 })
+        );
+        assert_eq!(parse(&escape("a{1}")), parse(r"a\{1}"));
+
+        assert_eq!(
+            parse("a{1,1000000}"),
+            Err(ParseError::RepetitionTooLarge { at: 1 })
+        );
+        // The nested repetitions multiply
+        assert_eq!(
+            parse("(a{1000}){1000}"),
+            Err(ParseError::RepetitionTooLarge { at: 9 })
+        );
+        assert!(parse_with_limit("(ab){3}", 15).is_ok());
+        assert_eq!(
+            parse_with_limit("(ab){3}", 14),
+            Err(ParseError::RepetitionTooLarge { at: 4 })
+        );
+        let nfa = crate::nfa::NFA::new("a{33000}").unwrap();
+        assert!(nfa.run("a".repeat(33000)));
+        assert!(!nfa.run("a".repeat(32999)));
+        // The limit applies to all the repetitions together, not to each one
+        assert!(parse_with_limit("a{4}", 12).is_ok());
+        assert_eq!(
+            parse_with_limit("a{4}b{4}", 20),
+            Err(ParseError::RepetitionTooLarge { at: 5 })
+        );
+        assert_eq!(
+            parse("a{30000}b{30000}"),
+            Err(ParseError::RepetitionTooLarge { at: 9 })
+        );
+    }
+
+    #[test]
+    fn nesting_depth_test() {
+        let nested = |depth: usize| format!("{}a{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(crate::nfa::NFA::new(&nested(MAX_NESTING_DEPTH))
+            .unwrap()
+            .run("a"));
+        assert_eq!(
+            parse(&nested(MAX_NESTING_DEPTH + 1)),
+            Err(ParseError::NestingTooDeep {
+                at: MAX_NESTING_DEPTH
+            })
+        );
+        // Far deeper than the stack would allow without the limit
+        assert_eq!(
+            parse(&"(".repeat(1_000_000)),
+            Err(ParseError::NestingTooDeep {
+                at: MAX_NESTING_DEPTH
+            })
+        );
+    }
+
+    #[test]
+    fn long_pattern_test() {
+        // Long chains of alternatives, concatenations and operators, which overflowed the stack
+        // when the passes over the tree recursed once per node
+        let alternatives = vec!["a"; 20_000].join("|");
+        assert!(crate::nfa::NFA::new(&alternatives).unwrap().run("a"));
+        let literal = "a".repeat(100_000);
+        assert!(crate::nfa::NFA::new(&literal).unwrap().run(&literal));
+        let questions = format!("a{}", "?".repeat(20_000));
+        assert!(crate::nfa::NFA::new(&questions).unwrap().run(""));
+        let repeated = format!("{}{{2}}", questions);
+        assert!(crate::nfa::NFA::new(&repeated).unwrap().run("aa"));
+        use crate::builder::{CompileError, NullableStars, RegexBuilder};
+        let builder = RegexBuilder::new().nullable_stars(NullableStars::Collapse);
+        assert!(builder.build_pattern(&alternatives).unwrap().run("a"));
+        assert!(builder.build_pattern(&questions).unwrap().run("a"));
+        let builder = RegexBuilder::new().nullable_stars(NullableStars::Reject);
+        assert_eq!(
+            builder.build_pattern(&questions).unwrap_err(),
+            CompileError::NullableStar
+        );
+    }
+
+    #[test]
+    fn class_size_test() {
+        assert_eq!(
+            parse("a[\u{0}-\u{10FFFF}]"),
+            Err(ParseError::ClassTooLarge { at: 1 })
+        );
+        assert_eq!(
+            parse("[a-z\u{4e00}-\u{9fff}]"),
+            Err(ParseError::ClassTooLarge { at: 0 })
+        );
+        let largest = format!(
+            "[\u{100}-{}]",
+            char::from_u32(0x100 + MAX_CLASS_SIZE as u32 - 1).unwrap()
+        );
+        let nfa = crate::nfa::NFA::new(&largest).unwrap();
+        assert!(nfa.run("\u{100}"));
+        assert!(!nfa.run("a"));
+    }
+
+    #[test]
+    fn flag_group_test() {
+        let nfa = crate::nfa::NFA::new("(?i:ab)c").unwrap();
+        assert!(nfa.run("ABc"));
+        assert!(nfa.run("aBc"));
+        assert!(!nfa.run("ABC"));
+
+        let nfa = crate::nfa::NFA::new("(?i:a(?-i:b)[x-y])+").unwrap();
+        assert!(nfa.run("AbYabx"));
+        assert!(!nfa.run("AB"));
+        // The flag is restored after the group
+        let nfa = crate::nfa::NFA::new("(?i:a)a|(?i:1)").unwrap();
+        assert!(nfa.run("Aa"));
+        assert!(!nfa.run("AA"));
+        assert!(nfa.run("1"));
+
+        assert_eq!(
+            parse("(?x:a)"),
+            Err(ParseError::UnknownFlag { flag: 'x', at: 2 })
+        );
+        assert_eq!(
+            parse("(?i"),
+            Err(ParseError::UnbalancedParenthesis { at: 0 })
+        );
+    }
+
+    #[test]
+    fn escape_test() {
+        assert_eq!(escape("a.b*"), "a\\.b\\*");
+        let nfa = crate::nfa::NFA::new(&escape("a.b*")).unwrap();
+        assert!(nfa.run("a.b*"));
+        assert!(!nfa.run("a.bb"));
+        let text = "(x|[y])\\";
+        assert_eq!(parse(&escape(text)), Ok(Node::from_str_literal(text)));
+    }
+
+    #[test]
+    fn parse_test() {
+        // `ab|c*` reads as `(ab)|(c*)`
+        assert_eq!(
+            parse("ab|c*"),
+            Ok(Node::Operation(
+                Operator::Or,
+                Box::new(Node::Operation(
+                    Operator::Concat,
+                    Box::new(t('a', 1)),
+                    Some(Box::new(t('b', 2))),
+                )),
+                Some(Box::new(Node::Operation(
+                    Operator::Production,
+                    Box::new(t('c', 3)),
+                    None,
+                ))),
+            ))
+        );
+        assert_eq!(
+            parse("(ab)*"),
+            Ok(Node::Operation(
+                Operator::Production,
+                Box::new(Node::Operation(
+                    Operator::Concat,
+                    Box::new(t('a', 1)),
+                    Some(Box::new(t('b', 2))),
+                )),
+                None,
+            ))
+        );
+        assert_eq!(parse(r"\*\\"), Ok(Node::from_str_literal(r"*\")));
+        assert_eq!(parse(""), Ok(Node::Epsilon));
+        assert_eq!(parse("()"), Ok(Node::Epsilon));
+    }
+
+    #[test]
+    fn parse_error_test() {
+        assert_eq!(
+            parse("(ab"),
+            Err(ParseError::UnbalancedParenthesis { at: 0 })
+        );
+        assert_eq!(
+            parse("ab)c"),
+            Err(ParseError::UnbalancedParenthesis { at: 2 })
+        );
+        assert_eq!(
+            parse("a||b"),
+            Err(ParseError::DanglingOperator { op: '|', at: 1 })
+        );
+        assert_eq!(
+            parse("|a"),
+            Err(ParseError::DanglingOperator { op: '|', at: 0 })
+        );
+        assert_eq!(
+            parse("a(*)"),
+            Err(ParseError::DanglingOperator { op: '*', at: 2 })
+        );
+        assert_eq!(parse(r"a\"), Err(ParseError::TrailingEscape { at: 1 }));
+    }
+
+    /// Parses the class at the start of the pattern.
+    fn class(pattern: &str) -> Result<CharClass, ParseError> {
+        let mut parser = Parser {
+            chars: pattern.char_indices().peekable(),
+            ignore_case: false,
+            ascii_only: false,
+            max_repetition_nodes: MAX_REPETITION_NODES,
+            repetition_nodes: 0,
+            depth: 0,
+        };
+        parser.chars.next();
+        parser.class(0)
+    }
+
+    #[test]
+    fn class_test() {
+        let class_chars = |pattern: &str| -> String {
+            let class = class(pattern).unwrap();
+            ['a', 'b', 'y', 'z', '-', ']', '^', '\\']
+                .into_iter()
+                .filter(|&c| class.contains(c))
+                .collect()
+        };
+        assert_eq!(class_chars(r"[a\-z]"), "az-");
+        assert_eq!(class_chars("[a-z]"), "abyz");
+        assert_eq!(class_chars("[-az]"), "az-");
+        assert_eq!(class_chars("[az-]"), "az-");
+        assert_eq!(class_chars("[]a]"), "a]");
+        assert_eq!(class_chars(r"[\]\\]"), "]\\");
+        assert_eq!(class_chars("[a^]"), "a^");
+        let negated = class("[^]]").unwrap();
+        assert!(negated.negated);
+        assert!(!negated.contains(']'));
+        assert!(negated.contains('a'));
+    }
+
+    #[test]
+    fn class_parse_test() {
+        let nfa = crate::regex(&parse("x[a-c]*").unwrap());
+        assert!(nfa.run("xabcca"));
+        assert!(!nfa.run("xd"));
+        assert_eq!(parse("[ab"), Err(ParseError::UnbalancedClass { at: 0 }));
+        assert_eq!(parse("a[]"), Err(ParseError::UnbalancedClass { at: 1 }));
+        assert_eq!(parse("[a"), Err(ParseError::UnbalancedClass { at: 0 }));
+        assert_eq!(parse("a]"), Err(ParseError::UnbalancedClass { at: 1 }));
+        assert_eq!(parse("[a]]"), Err(ParseError::UnbalancedClass { at: 3 }));
+        assert_eq!(parse(r"a\]"), Ok(Node::from_str_literal("a]")));
+        assert_eq!(parse("[z-a]"), Err(ParseError::InvalidRange { at: 1 }));
+        assert_eq!(parse("[^a]"), Err(ParseError::NegatedClass { at: 0 }));
+    }
+
+    #[test]
+    fn try_from_test() {
+        let tree: Node = "(a|b)*c".try_into().unwrap();
+        let nfa = crate::regex(&tree);
+        assert!(nfa.run("abbac"));
+        assert!(!nfa.run("ab"));
+        assert!(Node::try_from("a|").is_err());
+    }
+}
@@ -0,0 +1,274 @@
+//! Contains the `RegexSet` struct, which matches an input against many patterns at once.
+
+use crate::nfa::NFA;
+use std::collections::{HashMap, HashSet};
+
+/// The `RegexSet` struct combines many [NFA]s into a single automaton, so all the patterns are
+/// simulated together in one pass over the input.
+#[derive(Debug, Default)]
+pub struct RegexSet {
+    /// Start state of every pattern, in the order the patterns were given.
+    start: Vec<u32>,
+    /// Map from an accepting state to the index of the pattern it belongs to.
+    accept: HashMap<u32, usize>,
+    /// The transition function is a map from a pair of a state and a character to a set of states.
+    transition_function: HashMap<(u32, char), HashSet<u32>>,
+    /// Map from the accepting states that every input over the alphabet of their pattern keeps
+    /// an accepting state live from, see [sticky_states], to the index of their pattern.
+    sticky: HashMap<u32, usize>,
+    /// Characters of every pattern, in the order the patterns were given.
+    alphabets: Vec<HashSet<char>>,
+}
+
+impl RegexSet {
+    /// Combines the given NFAs, renumbering their states so they do not overlap.
+    pub fn new(nfas: Vec<NFA>) -> Self {
+        let mut set = Self::default();
+        let mut offset = 0;
+        for (index, nfa) in nfas.iter().enumerate() {
+            set.start.push(offset);
+            for state in &nfa.accept {
+                set.accept.insert(offset + state, index);
+            }
+            for (&(from, c), to) in &nfa.transition_function {
+                set.transition_function
+                    .entry((offset + from, c))
+                    .or_default()
+                    .extend(to.iter().map(|state| offset + state));
+            }
+            let alphabet = nfa.transition_function.keys().map(|&(_, c)| c).collect();
+            for state in sticky_states(nfa, &alphabet) {
+                set.sticky.insert(offset + state, index);
+            }
+            set.alphabets.push(alphabet);
+            offset += nfa.states.iter().copied().max().unwrap_or(0) + 1;
+        }
+        set
+    }
+
+    /// Returns the number of patterns in the set.
+    pub fn len(&self) -> usize {
+        self.start.len()
+    }
+
+    /// Returns true if the set has no patterns.
+    pub fn is_empty(&self) -> bool {
+        self.start.is_empty()
+    }
+
+    /// Returns the sorted indices of the patterns matching the input.
+    pub fn matches(&self, input: impl AsRef<str>) -> Vec<usize> {
+        let input = input.as_ref();
+        let mut matches: Vec<usize> = self
+            .final_states(input)
+            .iter()
+            .filter_map(|state| self.accept.get(state))
+            .copied()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        matches.sort_unstable();
+        matches
+    }
+
+    /// Returns true if any pattern matches the input.
+    ///
+    /// The simulation stops as soon as no pattern can match anymore, or as soon as a pattern
+    /// reaches a state it cannot leave without accepting, if the rest of the input only has
+    /// characters of that pattern.
+    pub fn is_match(&self, input: impl AsRef<str>) -> bool {
+        self.simulate_match(input.as_ref()).0
+    }
+
+    /// Decides [RegexSet::is_match], also returning the number of characters the simulation read.
+    fn simulate_match(&self, input: &str) -> (bool, usize) {
+        let mut current_states: HashSet<u32> = self.start.iter().copied().collect();
+        let mut checked = vec![false; self.len()];
+        let mut read = 0;
+        for (at, c) in input.char_indices() {
+            if current_states.is_empty() {
+                return (false, read);
+            }
+            for state in &current_states {
+                let Some(&pattern) = self.sticky.get(state) else {
+                    continue;
+                };
+                // A character outside its alphabet kills the pattern for good, so the rest of the
+                // input only needs to be checked once per pattern
+                if !std::mem::replace(&mut checked[pattern], true)
+                    && input[at..]
+                        .chars()
+                        .all(|c| self.alphabets[pattern].contains(&c))
+                {
+                    return (true, read);
+                }
+            }
+            current_states = self.step(current_states, c);
+            read += 1;
+        }
+        let matched = current_states
+            .iter()
+            .any(|state| self.accept.contains_key(state));
+        (matched, read)
+    }
+
+    /// Returns true if every pattern of the set matches somewhere in the input.
+    ///
+    /// All the patterns are searched for together in a single pass, a new run of every pattern
+    /// starting at each character.
+    pub fn all_match(&self, input: impl AsRef<str>) -> bool {
+        let input = input.as_ref();
+        let mut found: HashSet<usize> = HashSet::new();
+        let mut current_states: HashSet<u32> = HashSet::new();
+        for c in input.chars().map(Some).chain(std::iter::once(None)) {
+            current_states.extend(&self.start);
+            found.extend(
+                current_states
+                    .iter()
+                    .filter_map(|state| self.accept.get(state)),
+            );
+            if found.len() == self.len() {
+                return true;
+            }
+            let Some(c) = c else {
+                break;
+            };
+            let mut next_states = HashSet::new();
+            for state in current_states {
+                if let Some(states) = self.transition_function.get(&(state, c)) {
+                    next_states.extend(states);
+                }
+            }
+            current_states = next_states;
+        }
+        false
+    }
+
+    /// Simulates all the patterns together, returning the states reached after reading the input.
+    fn final_states(&self, input: &str) -> HashSet<u32> {
+        let mut current_states: HashSet<u32> = self.start.iter().copied().collect();
+        for c in input.chars() {
+            if current_states.is_empty() {
+                break;
+            }
+            current_states = self.step(current_states, c);
+        }
+        current_states
+    }
+
+    /// Returns the states reached from `current_states` by reading `c`.
+    fn step(&self, current_states: HashSet<u32>, c: char) -> HashSet<u32> {
+        let mut next_states = HashSet::new();
+        for state in current_states {
+            if let Some(states) = self.transition_function.get(&(state, c)) {
+                next_states.extend(states);
+            }
+        }
+        next_states
+    }
+}
+
+/// Returns the accepting states of the NFA from which every input over `alphabet` keeps an
+/// accepting state live.
+///
+/// These are the largest set of accepting states having, on every character of `alphabet`, a
+/// transition back into the set. The set starts from all the accepting states, and the states
+/// missing such a transition are removed until none is.
+fn sticky_states(nfa: &NFA, alphabet: &HashSet<char>) -> HashSet<u32> {
+    let mut sticky = nfa.accept.clone();
+    loop {
+        let kept: HashSet<u32> = sticky
+            .iter()
+            .copied()
+            .filter(|&state| {
+                alphabet.iter().all(|&c| {
+                    nfa.transition_function
+                        .get(&(state, c))
+                        .is_some_and(|to| !to.is_disjoint(&sticky))
+                })
+            })
+            .collect();
+        if kept.len() == sticky.len() {
+            return kept;
+        }
+        sticky = kept;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translation::node::Node;
+    use crate::translation::operator::Operator;
+
+    fn sample_set() -> RegexSet {
+        // ab
+        let ab = crate::regex(&Node::Operation(
+            Operator::Concat,
+            Box::new(Node::Terminal('a', 1)),
+            Some(Box::new(Node::Terminal('b', 2))),
+        ));
+        // a*b
+        let a_star_b = crate::regex(&Node::Operation(
+            Operator::Concat,
+            Box::new(Node::Operation(
+                Operator::Production,
+                Box::new(Node::Terminal('a', 1)),
+                None,
+            )),
+            Some(Box::new(Node::Terminal('b', 2))),
+        ));
+        // a|c
+        let a_or_c = crate::regex(&Node::Operation(
+            Operator::Or,
+            Box::new(Node::Terminal('a', 1)),
+            Some(Box::new(Node::Terminal('c', 2))),
+        ));
+        RegexSet::new(vec![ab, a_star_b, a_or_c])
+    }
+
+    #[test]
+    fn matches_test() {
+        let set = sample_set();
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.matches("ab"), vec![0, 1]);
+        assert_eq!(set.matches("aab"), vec![1]);
+        assert_eq!(set.matches("c"), vec![2]);
+        assert_eq!(set.matches("ba"), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn all_match_test() {
+        let set = sample_set();
+        assert!(set.all_match("xxabyc"));
+        assert!(set.all_match("cab"));
+        assert!(!set.all_match("xbyc"));
+        assert!(RegexSet::new(vec![]).all_match(""));
+    }
+
+    #[test]
+    fn is_match_test() {
+        let set = sample_set();
+        assert!(set.is_match("ab"));
+        assert!(set.is_match("c"));
+        assert!(!set.is_match("ba"));
+        assert!(!set.is_match(""));
+        assert!(!RegexSet::new(vec![]).is_match("ab"));
+    }
+
+    #[test]
+    fn is_match_early_exit_test() {
+        let any = crate::nfa::NFA::new("(a|b)*").unwrap();
+        let set = RegexSet::new(vec![crate::nfa::NFA::new("c+").unwrap(), any]);
+        // The star accepts every input over `a` and `b` from its start, so nothing is simulated
+        let input = "ab".repeat(10_000);
+        assert_eq!(set.simulate_match(&input), (true, 0));
+        let input = format!("c{}", input);
+        assert_eq!(set.simulate_match(&input), (false, 2));
+        // The `c` kills the star, which the simulation then follows to the end
+        let input = format!("ab{}c", "ab".repeat(100));
+        assert_eq!(set.simulate_match(&input), (false, 203));
+        assert!(set.is_match("ccc"));
+        assert!(set.is_match(""));
+    }
+}
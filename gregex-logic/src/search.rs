@@ -0,0 +1,612 @@
+//! Contains the `Match` and `Matches` structs, and the methods searching for matches of a [NFA] inside a larger input.
+
+use crate::nfa::NFA;
+use std::collections::HashMap;
+use std::io::{self, BufRead};
+use std::ops::Range;
+
+/// The `MatchSemantics` enum selects which match is reported at the leftmost position where the NFA matches.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MatchSemantics {
+    /// Report the shortest match, the first one found when reading the input. The NFA gives no
+    /// priority to the branches of an alternation, so `a|ab` matches `"a"` in `"ab"` just as
+    /// `ab|a` does.
+    LeftmostShortest,
+    /// Report the longest match, like POSIX. `a|ab` matches `"ab"` in `"ab"`.
+    #[default]
+    LeftmostLongest,
+}
+
+/// The `Match` struct represents a match of a NFA in a haystack.
+///
+/// Its offsets are in bytes, like the ones of [str::find], see [Match::char_range] for characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match<'h> {
+    haystack: &'h str,
+    start: usize,
+    end: usize,
+}
+
+/// The `Segment` enum represents a part of a haystack split by [NFA::split_at_matches].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment<'h> {
+    /// Text between two matches.
+    Text(&'h str),
+    /// A match.
+    Delim(&'h str),
+}
+
+/// The `Matches` struct is the iterator over the successive non-overlapping matches of a NFA in a
+/// haystack, see [NFA::find_iter].
+///
+/// The matches borrow the haystack, not the NFA, so they outlive the iterator.
+#[derive(Debug)]
+pub struct Matches<'a, 'h> {
+    nfa: &'a NFA,
+    haystack: &'h str,
+    /// Byte offset the next search starts from, `None` once the haystack is exhausted.
+    at: Option<usize>,
+}
+
+impl<'h> Iterator for Matches<'_, 'h> {
+    type Item = Match<'h>;
+
+    fn next(&mut self) -> Option<Match<'h>> {
+        let found = self.nfa.find_at(self.haystack, self.at?)?;
+        self.at = if found.start == found.end {
+            self.haystack[found.end..]
+                .chars()
+                .next()
+                .map(|c| found.end + c.len_utf8())
+        } else {
+            Some(found.end)
+        };
+        Some(found)
+    }
+}
+
+impl<'h> Match<'h> {
+    /// Returns the byte offset of the start of the match.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the byte offset just after the end of the match.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Returns the byte range of the match, which can be used to slice the haystack.
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// Returns the range of the match in characters rather than bytes, counting the characters of
+    /// the haystack before and inside the match.
+    ///
+    /// This range cannot be used to slice the haystack when it has multi-byte characters.
+    pub fn char_range(&self) -> Range<usize> {
+        let start = self.haystack[..self.start].chars().count();
+        start..start + self.as_str().chars().count()
+    }
+
+    /// Returns the matched text.
+    pub fn as_str(&self) -> &'h str {
+        &self.haystack[self.start..self.end]
+    }
+}
+
+impl NFA {
+    /// Sets which match is reported by the search methods. The default is [MatchSemantics::LeftmostLongest].
+    pub fn with_match_semantics(mut self, semantics: MatchSemantics) -> Self {
+        self.semantics = semantics;
+        self
+    }
+
+    /// Returns the leftmost match in the haystack, chosen at that position according to the
+    /// [MatchSemantics] of the NFA.
+    pub fn find<'h>(&self, haystack: &'h str) -> Option<Match<'h>> {
+        self.find_at(haystack, 0)
+    }
+
+    /// Returns the same match as [NFA::find], but in a single left to right pass over the haystack.
+    ///
+    /// Rather than running the NFA again from every offset, a run is started at every offset and
+    /// each state only remembers the earliest offset a run reaching it started from.
+    pub fn leftmost_find<'h>(&self, haystack: &'h str) -> Option<Match<'h>> {
+        let mut starts: HashMap<u32, usize> = HashMap::new();
+        let mut best: Option<(usize, usize)> = None;
+        let offsets = haystack
+            .char_indices()
+            .map(|(i, c)| (i, Some(c)))
+            .chain(std::iter::once((haystack.len(), None)));
+        for (at, c) in offsets {
+            if !self.leftmost_step(&mut starts, &mut best, at, c) {
+                break;
+            }
+        }
+        best.map(|(start, end)| Match {
+            haystack,
+            start,
+            end,
+        })
+    }
+
+    /// Returns the byte offset and the text of the first match in the stream, the same match
+    /// [NFA::leftmost_find] would find in the whole text.
+    ///
+    /// The stream is read once. Only the text from the start of the earliest run that can still
+    /// give a match is kept, so for patterns with a finite maximum match length, see
+    /// [NFA::match_len_bounds], the memory used does not grow with the stream. Matches spanning
+    /// the buffers of the reader are found like any other.
+    ///
+    /// The stream must be valid UTF-8, otherwise an [io::ErrorKind::InvalidData] error is returned.
+    pub fn find_in_reader<R: BufRead>(&self, mut reader: R) -> io::Result<Option<(u64, String)>> {
+        let mut starts: HashMap<u32, u64> = HashMap::new();
+        let mut best: Option<(u64, u64)> = None;
+        // Text read from the byte offset `window_start` of the stream
+        let mut window = String::new();
+        let mut window_start: u64 = 0;
+        let mut at: u64 = 0;
+        // Bytes of a character split between two buffers
+        let mut pending: Vec<u8> = Vec::new();
+        loop {
+            let buffer = reader.fill_buf()?;
+            let consumed = buffer.len();
+            let at_end = consumed == 0;
+            pending.extend_from_slice(buffer);
+            reader.consume(consumed);
+            let valid = match std::str::from_utf8(&pending) {
+                Ok(text) => text.len(),
+                Err(err) if err.error_len().is_none() && !at_end => err.valid_up_to(),
+                Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+            };
+            let text = std::str::from_utf8(&pending[..valid]).expect("checked to be valid UTF-8");
+            let chars = text.chars().map(Some);
+            let chars: Box<dyn Iterator<Item = Option<char>>> = if at_end {
+                Box::new(chars.chain(std::iter::once(None)))
+            } else {
+                Box::new(chars)
+            };
+            let mut stopped = false;
+            for c in chars {
+                if !self.leftmost_step(&mut starts, &mut best, at, c) {
+                    stopped = true;
+                    break;
+                }
+                if let Some(c) = c {
+                    window.push(c);
+                    at += c.len_utf8() as u64;
+                }
+            }
+            if stopped || at_end {
+                return Ok(best.map(|(start, end)| {
+                    let range = (start - window_start) as usize..(end - window_start) as usize;
+                    (start, window[range].to_string())
+                }));
+            }
+            pending.drain(..valid);
+
+            // Forget the text before the earliest offset a match can still start from
+            let keep_from = starts
+                .values()
+                .copied()
+                .chain(best.map(|(start, _)| start))
+                .min()
+                .unwrap_or(at);
+            window.drain(..(keep_from - window_start) as usize);
+            window_start = keep_from;
+        }
+    }
+
+    /// Moves the runs of [NFA::leftmost_find] over the character `c` at the offset `at`, `None` at
+    /// the end of the input, returning false once no better match can be found.
+    fn leftmost_step<T: Copy + Ord>(
+        &self,
+        starts: &mut HashMap<u32, T>,
+        best: &mut Option<(T, T)>,
+        at: T,
+        c: Option<char>,
+    ) -> bool {
+        if best.is_none() {
+            starts.entry(0).or_insert(at);
+        }
+        for (state, &start) in starts.iter() {
+            if !self.accept.contains(state) {
+                continue;
+            }
+            *best = match *best {
+                Some((best_start, _)) if start < best_start => Some((start, at)),
+                Some((best_start, _))
+                    if start == best_start && self.semantics == MatchSemantics::LeftmostLongest =>
+                {
+                    Some((start, at))
+                }
+                None => Some((start, at)),
+                best => best,
+            };
+        }
+        // Runs starting after the best match can no longer give a leftmost match
+        if let Some((best_start, _)) = *best {
+            starts.retain(|_, start| *start <= best_start);
+            if starts.is_empty() {
+                return false;
+            }
+        }
+        let Some(c) = c else {
+            return false;
+        };
+        let mut next_starts: HashMap<u32, T> = HashMap::new();
+        for (&state, &start) in starts.iter() {
+            for &next in self
+                .transition_function
+                .get(&(state, c))
+                .into_iter()
+                .flatten()
+            {
+                let entry = next_starts.entry(next).or_insert(start);
+                *entry = (*entry).min(start);
+            }
+        }
+        *starts = next_starts;
+        true
+    }
+
+    /// Returns an iterator over the successive non-overlapping matches in the haystack.
+    ///
+    /// After an empty match the search resumes one character further, so the iterator always ends.
+    pub fn find_iter<'a, 'h>(&'a self, haystack: &'h str) -> Matches<'a, 'h> {
+        Matches {
+            nfa: self,
+            haystack,
+            at: Some(0),
+        }
+    }
+
+    /// Returns the rightmost of the non-overlapping matches, the last one [NFA::find_iter] yields.
+    ///
+    /// Where the non-overlapping matches lie depends on the earlier ones, so they are all searched
+    /// for from the start of the haystack.
+    pub fn find_last<'h>(&self, haystack: &'h str) -> Option<Match<'h>> {
+        self.find_iter(haystack).last()
+    }
+
+    /// Returns the byte offset and the text of the successive non-overlapping matches, like [str::match_indices].
+    pub fn match_indices<'h>(&self, haystack: &'h str) -> Vec<(usize, &'h str)> {
+        self.find_iter(haystack)
+            .map(|found| (found.start, found.as_str()))
+            .collect()
+    }
+
+    /// Returns the substrings of the haystack separated by the successive non-overlapping matches, like [str::split].
+    pub fn split<'h>(&self, haystack: &'h str) -> Vec<&'h str> {
+        let mut pieces = Vec::new();
+        let mut last = 0;
+        for found in self.find_iter(haystack) {
+            pieces.push(&haystack[last..found.start]);
+            last = found.end;
+        }
+        pieces.push(&haystack[last..]);
+        pieces
+    }
+
+    /// Returns the substrings of the haystack separated by the matches like [NFA::split], but
+    /// without the trailing empty substring when the haystack ends with a match, like [str::split_terminator].
+    pub fn split_terminator<'h>(&self, haystack: &'h str) -> Vec<&'h str> {
+        let mut pieces = self.split(haystack);
+        if pieces.last() == Some(&"") {
+            pieces.pop();
+        }
+        pieces
+    }
+
+    /// Returns the text between the matches interleaved with the matches themselves, so
+    /// concatenating the segments gives back the haystack. Empty texts are left out.
+    pub fn split_at_matches<'h>(&self, haystack: &'h str) -> Vec<Segment<'h>> {
+        let mut segments = Vec::new();
+        let mut last = 0;
+        for found in self.find_iter(haystack) {
+            if found.start > last {
+                segments.push(Segment::Text(&haystack[last..found.start]));
+            }
+            segments.push(Segment::Delim(found.as_str()));
+            last = found.end;
+        }
+        if last < haystack.len() {
+            segments.push(Segment::Text(&haystack[last..]));
+        }
+        segments
+    }
+
+    /// Returns the haystack with its first `limit` non-overlapping matches replaced by `replacement`.
+    pub fn replacen(&self, haystack: &str, replacement: &str, limit: usize) -> String {
+        let mut replaced = String::with_capacity(haystack.len());
+        let mut last = 0;
+        for found in self.find_iter(haystack).take(limit) {
+            replaced.push_str(&haystack[last..found.start]);
+            replaced.push_str(replacement);
+            last = found.end;
+        }
+        replaced.push_str(&haystack[last..]);
+        replaced
+    }
+
+    /// Returns the haystack with every non-overlapping match replaced by what `f` returns for the matched text.
+    pub fn replace_all_with<F: FnMut(&str) -> String>(&self, haystack: &str, mut f: F) -> String {
+        let mut replaced = String::with_capacity(haystack.len());
+        let mut last = 0;
+        for found in self.find_iter(haystack) {
+            replaced.push_str(&haystack[last..found.start]);
+            replaced.push_str(&f(found.as_str()));
+            last = found.end;
+        }
+        replaced.push_str(&haystack[last..]);
+        replaced
+    }
+
+    /// Returns the leftmost match starting at or after the byte offset `at`.
+    pub(crate) fn find_at<'h>(&self, haystack: &'h str, at: usize) -> Option<Match<'h>> {
+        if haystack.len() - at < self.min_len {
+            return None;
+        }
+        if let Some(literals) = &self.literals {
+            return literals
+                .find_at(haystack, at, self.semantics)
+                .map(|(start, end)| Match {
+                    haystack,
+                    start,
+                    end,
+                });
+        }
+        haystack[at..]
+            .char_indices()
+            .map(|(i, _)| at + i)
+            .chain(std::iter::once(haystack.len()))
+            .find_map(|start| {
+                let rest = &haystack[start..];
+                let len = match self.semantics {
+                    MatchSemantics::LeftmostShortest => self.shortest_prefix(rest),
+                    MatchSemantics::LeftmostLongest => self.longest_prefix(rest),
+                };
+                len.map(|len| Match {
+                    haystack,
+                    start,
+                    end: start + len,
+                })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translation::node::Node;
+    use crate::translation::operator::Operator;
+
+    /// NFA of `aa*`, i.e. `a+`.
+    fn a_plus() -> NFA {
+        crate::regex(&Node::Operation(
+            Operator::Concat,
+            Box::new(Node::Terminal('a', 1)),
+            Some(Box::new(Node::Operation(
+                Operator::Production,
+                Box::new(Node::Terminal('a', 2)),
+                None,
+            ))),
+        ))
+    }
+
+    #[test]
+    fn find_test() {
+        let nfa = a_plus();
+        let found = nfa.find("bbaab").unwrap();
+        assert_eq!(found.range(), 2..4);
+        assert_eq!(found.as_str(), "aa");
+        assert_eq!(nfa.find("bbb"), None);
+    }
+
+    #[test]
+    fn find_iter_borrow_test() {
+        let haystack = String::from("aXaaXa");
+        let pieces: Vec<&str> = {
+            // The NFA is dropped before the matches
+            let nfa = a_plus();
+            nfa.find_iter(&haystack)
+                .map(|found| found.as_str())
+                .collect()
+        };
+        assert_eq!(pieces, vec!["a", "aa", "a"]);
+        let range = haystack.as_bytes().as_ptr_range();
+        for piece in pieces {
+            assert!(range.contains(&piece.as_ptr()));
+        }
+    }
+
+    #[test]
+    fn char_range_test() {
+        let nfa = a_plus();
+        let haystack = "éaa→a";
+        let found = nfa.find(haystack).unwrap();
+        assert_eq!(found.range(), 2..4);
+        assert_eq!(found.char_range(), 1..3);
+        let found = nfa.find_last(haystack).unwrap();
+        assert_eq!(found.range(), 7..8);
+        assert_eq!(found.char_range(), 4..5);
+        assert_eq!(&haystack[found.range()], "a");
+    }
+
+    #[test]
+    fn find_in_reader_test() {
+        let nfa = a_plus();
+        let text = "xyz é baaab";
+        assert_eq!(
+            nfa.find_in_reader(text.as_bytes()).unwrap(),
+            Some((8, "aaa".to_string()))
+        );
+        // Buffers of one byte split the match, and the `é` between two buffers
+        for capacity in 1..4 {
+            let reader = io::BufReader::with_capacity(capacity, text.as_bytes());
+            assert_eq!(
+                nfa.find_in_reader(reader).unwrap(),
+                Some((8, "aaa".to_string()))
+            );
+        }
+        assert_eq!(nfa.find_in_reader("bbb".as_bytes()).unwrap(), None);
+        assert_eq!(
+            nfa.find_in_reader(&[b'a', 0xff][..]).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        // The match ends the stream
+        assert_eq!(
+            nfa.find_in_reader("bba".as_bytes()).unwrap(),
+            Some((2, "a".to_string()))
+        );
+    }
+
+    #[test]
+    fn find_last_test() {
+        let nfa = a_plus();
+        let found = nfa.find_last("aXaaXa").unwrap();
+        assert_eq!(found.range(), 5..6);
+        assert_eq!(nfa.find_last("aXaaX").unwrap().as_str(), "aa");
+        assert_eq!(nfa.find_last("XX"), None);
+    }
+
+    #[test]
+    fn leftmost_find_test() {
+        // (a|b)*c|ab
+        let tree = Node::Operation(
+            Operator::Or,
+            Box::new(Node::Operation(
+                Operator::Concat,
+                Box::new(Node::Operation(
+                    Operator::Production,
+                    Box::new(Node::Operation(
+                        Operator::Or,
+                        Box::new(Node::Terminal('a', 1)),
+                        Some(Box::new(Node::Terminal('b', 2))),
+                    )),
+                    None,
+                )),
+                Some(Box::new(Node::Terminal('c', 3))),
+            )),
+            Some(Box::new(Node::Operation(
+                Operator::Concat,
+                Box::new(Node::Terminal('a', 4)),
+                Some(Box::new(Node::Terminal('b', 5))),
+            ))),
+        );
+        let longest = crate::regex(&tree);
+        let shortest = crate::regex(&tree).with_match_semantics(MatchSemantics::LeftmostShortest);
+        for haystack in ["", "ab", "xabx", "xabbc", "xxcab", "bbb", "ababac", "xyz"] {
+            for nfa in [&longest, &shortest] {
+                assert_eq!(
+                    nfa.leftmost_find(haystack),
+                    nfa.find(haystack),
+                    "in {:?}",
+                    haystack
+                );
+            }
+        }
+        for haystack in ["", "aab", "baa", "bbb"] {
+            assert_eq!(a_plus().leftmost_find(haystack), a_plus().find(haystack));
+        }
+    }
+
+    #[test]
+    fn find_semantics_test() {
+        // a|ab
+        let nfa = crate::regex(&Node::Operation(
+            Operator::Or,
+            Box::new(Node::Terminal('a', 1)),
+            Some(Box::new(Node::Operation(
+                Operator::Concat,
+                Box::new(Node::Terminal('a', 2)),
+                Some(Box::new(Node::Terminal('b', 3))),
+            ))),
+        ));
+        assert_eq!(nfa.find("ab").unwrap().as_str(), "ab");
+
+        let nfa = nfa.with_match_semantics(MatchSemantics::LeftmostShortest);
+        assert_eq!(nfa.find("ab").unwrap().as_str(), "a");
+        assert_eq!(nfa.find("xab").unwrap().range(), 1..2);
+        // The order of the branches does not matter
+        let nfa = NFA::new("ab|a")
+            .unwrap()
+            .with_match_semantics(MatchSemantics::LeftmostShortest);
+        assert_eq!(nfa.find("ab").unwrap().as_str(), "a");
+    }
+
+    #[test]
+    fn find_iter_test() {
+        let spans: Vec<_> = a_plus().find_iter("abaab").map(|m| m.range()).collect();
+        assert_eq!(spans, vec![0..1, 2..4]);
+
+        // a*
+        let nfa = crate::regex(&Node::Operation(
+            Operator::Production,
+            Box::new(Node::Terminal('a', 1)),
+            None,
+        ));
+        let spans: Vec<_> = nfa.find_iter("baa").map(|m| m.range()).collect();
+        assert_eq!(spans, vec![0..0, 1..3, 3..3]);
+    }
+
+    #[test]
+    fn match_indices_test() {
+        assert_eq!(a_plus().match_indices("baaxa"), vec![(1, "aa"), (4, "a")]);
+        assert_eq!(a_plus().match_indices("bxb"), vec![]);
+    }
+
+    #[test]
+    fn split_test() {
+        let comma = crate::regex(&Node::Terminal(',', 1));
+        assert_eq!(comma.split("a,b,"), vec!["a", "b", ""]);
+        assert_eq!(comma.split_terminator("a,b,"), vec!["a", "b"]);
+        assert_eq!(comma.split_terminator("a,b"), vec!["a", "b"]);
+        assert_eq!(comma.split_terminator(""), Vec::<&str>::new());
+        assert_eq!(a_plus().split("baab"), vec!["b", "b"]);
+    }
+
+    #[test]
+    fn split_at_matches_test() {
+        let nfa = NFA::new("[,;]").unwrap();
+        assert_eq!(
+            nfa.split_at_matches("a,b;c"),
+            vec![
+                Segment::Text("a"),
+                Segment::Delim(","),
+                Segment::Text("b"),
+                Segment::Delim(";"),
+                Segment::Text("c"),
+            ]
+        );
+        assert_eq!(
+            nfa.split_at_matches(",,x"),
+            vec![Segment::Delim(","), Segment::Delim(","), Segment::Text("x")]
+        );
+        assert_eq!(nfa.split_at_matches(""), vec![]);
+    }
+
+    #[test]
+    fn replacen_test() {
+        let nfa = a_plus();
+        assert_eq!(nfa.replacen("aXaaXa", "-", 2), "-X-Xa");
+        assert_eq!(nfa.replacen("aXaaXa", "-", 0), "aXaaXa");
+        assert_eq!(nfa.replacen("aXaaXa", "-", 10), "-X-X-");
+    }
+
+    #[test]
+    fn replace_all_with_test() {
+        assert_eq!(
+            a_plus().replace_all_with("aabaa", |found| found.to_uppercase()),
+            "AAbAA"
+        );
+        let mut count = 0;
+        let numbered = a_plus().replace_all_with("xaxaax", |_| {
+            count += 1;
+            count.to_string()
+        });
+        assert_eq!(numbered, "x1x2x");
+    }
+}
@@ -0,0 +1,11 @@
+//! Contains the `Symbol` trait, implemented by the types a regular expression can be built over.
+
+use std::hash::Hash;
+
+/// The `Symbol` trait is implemented by the types the terminals of a [crate::translation::node::Node] can hold.
+///
+/// It is implemented for every `Eq + Hash + Clone` type, so besides `char`, the default, a pattern
+/// can match a stream of tokens or protocol events.
+pub trait Symbol: Eq + Hash + Clone {}
+
+impl<T: Eq + Hash + Clone> Symbol for T {}
@@ -0,0 +1,363 @@
+//! Contains the `NodeArena` struct, which stores a regular expression tree in a flat vector.
+
+use crate::nfa::NFA;
+use crate::symbol::Symbol;
+use crate::translation::node::Node;
+use crate::translation::operator::Operator;
+use crate::translation::setterminal::SetTerminal;
+use std::collections::HashSet;
+
+/// The `NodeId` struct is the index of a node in a [NodeArena].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(u32);
+
+/// The `NodeData` enum is a node of a [NodeArena], like a [Node] whose operands are ids.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeData<S = char> {
+    Operation(Operator, NodeId, Option<NodeId>),
+    Terminal(S, u32),
+    Epsilon,
+}
+
+/// The `GlushkovSets` struct holds the sets of a tree computed by [NodeArena::sets], the same as
+/// the ones [crate::translation::node::nullability_set] and the other set functions return.
+#[derive(Debug, PartialEq, Eq)]
+pub struct GlushkovSets<S: Symbol = char> {
+    pub nullability: HashSet<SetTerminal<S>>,
+    pub prefix: HashSet<SetTerminal<S>>,
+    pub suffix: HashSet<SetTerminal<S>>,
+    pub factors: HashSet<SetTerminal<S>>,
+}
+
+/// The `NodeArena` struct stores a regular expression tree as a vector of nodes, the operands of
+/// every node coming before it and the root last.
+///
+/// Large trees are a single allocation rather than a `Box` per node, and are walked in index order
+/// instead of recursively, so their depth does not grow the stack. Nodes the root does not lead to
+/// are ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeArena<S = char> {
+    nodes: Vec<NodeData<S>>,
+}
+
+impl<S> Default for NodeArena<S> {
+    fn default() -> Self {
+        Self { nodes: Vec::new() }
+    }
+}
+
+impl<S: Symbol> NodeArena<S> {
+    /// Creates an empty arena.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a node whose operands are already in the arena, returning its id. The last node added is the root.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an operand is not in the arena, or if the operation has the wrong number of
+    /// operands for its operator.
+    pub fn push(&mut self, node: NodeData<S>) -> NodeId {
+        if let NodeData::Operation(op, left, right) = &node {
+            let len = self.nodes.len() as u32;
+            assert!(
+                left.0 < len && right.is_none_or(|right| right.0 < len),
+                "operand not in the arena"
+            );
+            assert_eq!(
+                op.is_binary(),
+                right.is_some(),
+                "wrong number of operands for {:?}",
+                op
+            );
+        }
+        self.nodes.push(node);
+        NodeId(self.nodes.len() as u32 - 1)
+    }
+
+    /// Returns the number of nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns true if the arena has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Copies the tree into an arena, without recursion.
+    pub fn from_node(regex_tree: &Node<S>) -> Self {
+        let mut arena = Self::new();
+        // Nodes whose operands are pushed before them once `expanded` is true
+        let mut pending = vec![(regex_tree, false)];
+        let mut ids: Vec<NodeId> = Vec::new();
+        while let Some((node, expanded)) = pending.pop() {
+            match node {
+                Node::Terminal(symbol, code) => {
+                    ids.push(arena.push(NodeData::Terminal(symbol.clone(), *code)))
+                }
+                Node::Epsilon => ids.push(arena.push(NodeData::Epsilon)),
+                Node::Operation(op, left, right) if expanded => {
+                    let right = right
+                        .as_ref()
+                        .map(|_| ids.pop().expect("right operand pushed"));
+                    let left = ids.pop().expect("left operand pushed");
+                    ids.push(arena.push(NodeData::Operation(*op, left, right)));
+                }
+                Node::Operation(_, left, right) => {
+                    pending.push((node, true));
+                    if let Some(right) = right {
+                        pending.push((right, false));
+                    }
+                    pending.push((left, false));
+                }
+            }
+        }
+        arena
+    }
+
+    /// Returns, for every node, whether the root leads to it.
+    fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.nodes.len()];
+        if let Some(root) = reachable.last_mut() {
+            *root = true;
+        }
+        // The operands come before their parent, so a single pass from the root is enough
+        for (i, node) in self.nodes.iter().enumerate().rev() {
+            if let (true, NodeData::Operation(_, left, right)) = (reachable[i], node) {
+                reachable[left.0 as usize] = true;
+                if let Some(right) = right {
+                    reachable[right.0 as usize] = true;
+                }
+            }
+        }
+        reachable
+    }
+
+    /// Rebuilds the `Box` based tree of the arena, without recursion. An empty arena gives [Node::Epsilon].
+    pub fn to_node(&self) -> Node<S> {
+        let reachable = self.reachable();
+        let mut built: Vec<Option<Node<S>>> = Vec::with_capacity(self.nodes.len());
+        for (node, reachable) in self.nodes.iter().zip(reachable) {
+            if !reachable {
+                built.push(None);
+                continue;
+            }
+            let mut take = |id: NodeId| built[id.0 as usize].take().expect("operand used once");
+            let node = match node {
+                NodeData::Terminal(symbol, code) => Node::Terminal(symbol.clone(), *code),
+                NodeData::Epsilon => Node::Epsilon,
+                NodeData::Operation(op, left, right) => {
+                    let left = Box::new(take(*left));
+                    Node::Operation(*op, left, right.map(|right| Box::new(take(right))))
+                }
+            };
+            built.push(Some(node));
+        }
+        built.pop().flatten().unwrap_or(Node::Epsilon)
+    }
+
+    /// Computes the nullability, prefix, suffix and factors sets of the tree in one pass over the
+    /// nodes, without recursion.
+    ///
+    /// Every operand has a single parent, so its prefix and suffix sets are moved into its parent's.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena is empty, or if an operand is shared by two nodes.
+    pub fn sets(&self) -> GlushkovSets<S> {
+        let mut nullable: Vec<bool> = Vec::with_capacity(self.nodes.len());
+        let mut prefixes: Vec<Option<HashSet<SetTerminal<S>>>> =
+            Vec::with_capacity(self.nodes.len());
+        let mut suffixes: Vec<Option<HashSet<SetTerminal<S>>>> =
+            Vec::with_capacity(self.nodes.len());
+        let mut factors: HashSet<SetTerminal<S>> = HashSet::new();
+        let reachable = self.reachable();
+        let take = |sets: &mut Vec<Option<HashSet<SetTerminal<S>>>>, id: NodeId| {
+            sets[id.0 as usize].take().expect("operand used once")
+        };
+        let products = |factors: &mut HashSet<SetTerminal<S>>,
+                        suffix: &HashSet<SetTerminal<S>>,
+                        prefix: &HashSet<SetTerminal<S>>| {
+            for i in suffix {
+                for j in prefix {
+                    factors.insert(i.product(j));
+                }
+            }
+        };
+
+        for (node, reachable) in self.nodes.iter().zip(reachable) {
+            if !reachable {
+                nullable.push(false);
+                prefixes.push(None);
+                suffixes.push(None);
+                continue;
+            }
+            let (node_nullable, prefix, suffix) = match node {
+                NodeData::Terminal(symbol, code) => {
+                    let terminal = || SetTerminal::SingleElement(symbol.clone(), *code);
+                    (
+                        false,
+                        HashSet::from([terminal()]),
+                        HashSet::from([terminal()]),
+                    )
+                }
+                NodeData::Epsilon => (true, HashSet::new(), HashSet::new()),
+                NodeData::Operation(op, left, Some(right)) => {
+                    let (left_nullable, right_nullable) =
+                        (nullable[left.0 as usize], nullable[right.0 as usize]);
+                    let mut prefix = take(&mut prefixes, *left);
+                    let left_suffix = take(&mut suffixes, *left);
+                    let right_prefix = take(&mut prefixes, *right);
+                    let mut suffix = take(&mut suffixes, *right);
+                    if *op == Operator::Or {
+                        prefix.extend(right_prefix);
+                        suffix.extend(left_suffix);
+                        (left_nullable || right_nullable, prefix, suffix)
+                    } else {
+                        products(&mut factors, &left_suffix, &right_prefix);
+                        if left_nullable {
+                            prefix.extend(right_prefix);
+                        }
+                        if right_nullable {
+                            suffix.extend(left_suffix);
+                        }
+                        (left_nullable && right_nullable, prefix, suffix)
+                    }
+                }
+                NodeData::Operation(op, operand, None) => {
+                    let prefix = take(&mut prefixes, *operand);
+                    let suffix = take(&mut suffixes, *operand);
+                    if *op != Operator::Question {
+                        products(&mut factors, &suffix, &prefix);
+                    }
+                    let operand_nullable = nullable[operand.0 as usize];
+                    (*op != Operator::Plus || operand_nullable, prefix, suffix)
+                }
+            };
+            nullable.push(node_nullable);
+            prefixes.push(Some(prefix));
+            suffixes.push(Some(suffix));
+        }
+
+        if factors.is_empty() {
+            factors.insert(SetTerminal::Empty);
+        }
+        let nullability = if *nullable.last().expect("the arena is empty") {
+            SetTerminal::Epsilon
+        } else {
+            SetTerminal::Empty
+        };
+        GlushkovSets {
+            nullability: HashSet::from([nullability]),
+            prefix: prefixes.pop().flatten().expect("the arena is empty"),
+            suffix: suffixes.pop().flatten().expect("the arena is empty"),
+            factors,
+        }
+    }
+
+    /// Converts the tree to its NFA like [crate::regex_over], computing the sets with [NodeArena::sets].
+    pub fn to_nfa(&self) -> NFA<S> {
+        let sets = self.sets();
+        let mut nfa = NFA::set_to_nfa(&sets.prefix, &sets.suffix, &sets.factors);
+        if sets.nullability.contains(&SetTerminal::Epsilon) {
+            nfa.accept.insert(0);
+        }
+        nfa
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translation::node::{factors_set, nullability_set, prefix_set, suffix_set};
+
+    #[test]
+    fn round_trip_test() {
+        let tree = crate::parser::parse("(a(ab)*)*|(ba)+c?").unwrap();
+        let arena = NodeArena::from_node(&tree);
+        assert_eq!(arena.len(), 15);
+        assert_eq!(arena.to_node(), tree);
+        assert_eq!(NodeArena::<char>::new().to_node(), Node::Epsilon);
+    }
+
+    #[test]
+    fn sets_test() {
+        for pattern in ["(a(ab)*)*|(ba)*", "a", "()", "(a|b)+c?d*", "a?b?", "(a*b)+"] {
+            let tree = crate::parser::parse(pattern).unwrap();
+            let sets = NodeArena::from_node(&tree).sets();
+            assert_eq!(sets.nullability, nullability_set(&tree), "{:?}", pattern);
+            assert_eq!(sets.prefix, prefix_set(&tree), "{:?}", pattern);
+            assert_eq!(sets.suffix, suffix_set(&tree), "{:?}", pattern);
+            assert_eq!(sets.factors, factors_set(&tree), "{:?}", pattern);
+        }
+    }
+
+    #[test]
+    fn large_pattern_test() {
+        // A long chain of `(a|b)*c` pieces, nested to the left
+        let mut arena = NodeArena::new();
+        let mut root: Option<NodeId> = None;
+        let mut code = 0;
+        let mut terminal = |arena: &mut NodeArena, symbol: char| {
+            code += 1;
+            arena.push(NodeData::Terminal(symbol, code))
+        };
+        for _ in 0..2000 {
+            let a = terminal(&mut arena, 'a');
+            let b = terminal(&mut arena, 'b');
+            let or = arena.push(NodeData::Operation(Operator::Or, a, Some(b)));
+            let star = arena.push(NodeData::Operation(Operator::Production, or, None));
+            let c = terminal(&mut arena, 'c');
+            let piece = arena.push(NodeData::Operation(Operator::Concat, star, Some(c)));
+            root = Some(match root {
+                Some(root) => arena.push(NodeData::Operation(Operator::Concat, root, Some(piece))),
+                None => piece,
+            });
+        }
+        let nfa = arena.to_nfa();
+        let boxed = crate::regex_over(&arena.to_node());
+        assert_eq!(nfa.transition_function, boxed.transition_function);
+        assert_eq!(nfa.accept, boxed.accept);
+        assert!(nfa.run_symbols("abc".repeat(2000).chars()));
+        assert!(!nfa.run_symbols("abc".repeat(1999).chars()));
+    }
+
+    #[test]
+    #[should_panic(expected = "operand not in the arena")]
+    fn push_test() {
+        let mut arena: NodeArena = NodeArena::new();
+        arena.push(NodeData::Operation(Operator::Production, NodeId(0), None));
+    }
+
+    #[test]
+    #[should_panic(expected = "wrong number of operands for Or")]
+    fn push_arity_test() {
+        let mut arena: NodeArena = NodeArena::new();
+        let a = arena.push(NodeData::Terminal('a', 1));
+        arena.push(NodeData::Operation(Operator::Or, a, None));
+    }
+
+    #[test]
+    fn unreachable_nodes_test() {
+        // `(a|b)*`, pushed before the root `cd`, is left out
+        let mut arena = NodeArena::new();
+        let a = arena.push(NodeData::Terminal('a', 1));
+        let b = arena.push(NodeData::Terminal('b', 2));
+        let or = arena.push(NodeData::Operation(Operator::Or, a, Some(b)));
+        arena.push(NodeData::Operation(Operator::Production, or, None));
+        let c = arena.push(NodeData::Terminal('c', 3));
+        let d = arena.push(NodeData::Terminal('d', 4));
+        arena.push(NodeData::Operation(Operator::Concat, c, Some(d)));
+
+        let tree = arena.to_node();
+        assert_eq!(tree.linearized(), "c3d4");
+        let sets = arena.sets();
+        assert_eq!(sets.factors, factors_set(&tree));
+        let nfa = arena.to_nfa();
+        assert!(nfa.run("cd"));
+        assert!(!nfa.run("ab"));
+        assert_eq!(nfa.stats().states, 3);
+    }
+}
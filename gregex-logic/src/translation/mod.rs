@@ -1,4 +1,5 @@
 //! Contains the translation submodules necessary to translate the raw regex to a NFA.
+pub mod arena;
+pub mod node;
 pub mod operator;
 pub mod setterminal;
-pub mod node;
\ No newline at end of file
@@ -1,165 +1,729 @@
 //! Contains the implementation of the `Node` enum and the functions to calculate the nullability, prefix, suffix and factors sets of a regular expression tree.
 
+use crate::symbol::Symbol;
 use crate::translation::operator::Operator;
 use crate::translation::setterminal::SetTerminal;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
 /// The `Node` enum represents the different types of nodes that can be used in a regular expression tree.
+///
+/// Terminals hold a `char` by default, but can hold any [Symbol].
 #[derive(Debug, PartialEq, Eq)]
-pub enum Node {
+pub enum Node<S = char> {
     /// Represents an operation on one or two nodes.
-    Operation(Operator, Box<Node>, Option<Box<Node>>),
-    /// `char` represents the character, `u32` represent the unique identifier of the node.
-    Terminal(char, u32),
+    Operation(Operator, Box<Node<S>>, Option<Box<Node<S>>>),
+    /// `S` represents the character, `u32` represent the unique identifier of the node.
+    Terminal(S, u32),
+    /// Represents the empty string ε.
+    Epsilon,
 }
 
-/// The `nullability_set` function returns the set of [SetTerminal] that are nullable in a regular expression tree.
-pub fn nullability_set(regex_tree: &Node) -> HashSet<SetTerminal> {
-    let mut set = HashSet::new();
-    match regex_tree {
-        Node::Terminal(_, _) => {
-            set.insert(SetTerminal::Empty);
+/// The `NodeVisitor` trait is implemented by analyses walking a regular expression tree with [Node::visit].
+///
+/// All methods do nothing by default, so a visitor only implements the ones it needs.
+pub trait NodeVisitor {
+    /// Called on every operation, before its operands are visited.
+    fn visit_operation(&mut self, _operator: &Operator) {}
+    /// Called on every terminal.
+    fn visit_terminal(&mut self, _symbol: char, _code: u32) {}
+    /// Called on every epsilon.
+    fn visit_epsilon(&mut self) {}
+}
+
+/// The `DecodeError` enum represents the reasons [Node::from_bytes] can refuse its input.
+#[derive(Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ends in the middle of a node.
+    UnexpectedEnd,
+    /// A node starts with an unknown tag byte.
+    InvalidTag(u8),
+    /// An operation has an unknown operator byte.
+    InvalidOperator(u8),
+    /// A terminal holds a value that is not a Unicode scalar value.
+    InvalidChar(u32),
+    /// The nodes do not form exactly one tree, an operation missing its operands or nodes being left over.
+    Malformed,
+    /// There are bytes after the last node.
+    TrailingBytes,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEnd => write!(f, "unexpected end of input"),
+            DecodeError::InvalidTag(tag) => write!(f, "invalid node tag {}", tag),
+            DecodeError::InvalidOperator(op) => write!(f, "invalid operator {}", op),
+            DecodeError::InvalidChar(c) => write!(f, "invalid character {:#x}", c),
+            DecodeError::Malformed => write!(f, "the nodes do not form a single tree"),
+            DecodeError::TrailingBytes => write!(f, "trailing bytes after the tree"),
         }
-        Node::Operation(op, left, right) => match op {
-            Operator::Or => {
-                set.extend(nullability_set(left));
-                set.extend(nullability_set(right.as_ref().unwrap()));
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+const EPSILON_TAG: u8 = 0;
+const TERMINAL_TAG: u8 = 1;
+const UNARY_TAG: u8 = 2;
+const BINARY_TAG: u8 = 3;
+const OPERATORS: [Operator; 5] = [
+    Operator::Or,
+    Operator::Concat,
+    Operator::Production,
+    Operator::Plus,
+    Operator::Question,
+];
+
+impl Node {
+    /// Walks the tree in pre-order, left operand first, calling the visitor on every node.
+    pub fn visit<V: NodeVisitor>(&self, visitor: &mut V) {
+        match self {
+            Node::Terminal(symbol, code) => visitor.visit_terminal(*symbol, *code),
+            Node::Epsilon => visitor.visit_epsilon(),
+            Node::Operation(op, left, right) => {
+                visitor.visit_operation(op);
+                left.visit(visitor);
+                if let Some(right) = right {
+                    right.visit(visitor);
+                }
             }
-            Operator::Concat => {
-                set.extend(nullability_set(left));
-                let right_set = nullability_set(right.as_ref().unwrap());
-                set.extend(right_set);
+        }
+    }
+
+    /// Returns the linearized pattern of the tree, every terminal followed by its code, like
+    /// `(a1b2)*|c3` for `(ab)*|c`. The empty string is written `ε`.
+    pub fn linearized(&self) -> String {
+        match self {
+            Node::Terminal(symbol, code) => format!("{}{}", symbol, code),
+            Node::Epsilon => "ε".to_string(),
+            Node::Operation(op, left, right) => {
+                // An operand binding less tightly than its operator needs parentheses
+                let operand = |node: &Node| match node {
+                    Node::Operation(inner, _, _) if inner.precedence() < op.precedence() => {
+                        format!("({})", node.linearized())
+                    }
+                    _ => node.linearized(),
+                };
+                match (op, right) {
+                    (Operator::Or, Some(right)) => {
+                        format!("{}|{}", operand(left), operand(right))
+                    }
+                    (_, Some(right)) => format!("{}{}", operand(left), operand(right)),
+                    (Operator::Production, None) => format!("{}*", operand(left)),
+                    (Operator::Plus, None) => format!("{}+", operand(left)),
+                    (_, None) => format!("{}?", operand(left)),
+                }
             }
-            Operator::Production => {
-                set.insert(SetTerminal::Epsilon);
+        }
+    }
+
+    /// Returns how many times every operator appears in the tree, to profile the patterns of a program.
+    /// Operators not in the tree are left out.
+    pub fn operator_counts(&self) -> HashMap<Operator, usize> {
+        struct Counter(HashMap<Operator, usize>);
+        impl NodeVisitor for Counter {
+            fn visit_operation(&mut self, operator: &Operator) {
+                *self.0.entry(*operator).or_default() += 1;
             }
-            _ => todo!(),
-        },
+        }
+
+        let mut counter = Counter(HashMap::new());
+        self.visit(&mut counter);
+        counter.0
     }
-    set
-}
 
-/// The `prefix_set` function returns the set of [SetTerminal] that are prefixes of a regular expression tree.
-pub fn prefix_set(regex_tree: &Node) -> HashSet<SetTerminal> {
-    let mut set = HashSet::new();
-    match regex_tree {
-        Node::Terminal(symbol, code) => {
-            set.insert(SetTerminal::SingleElement(*symbol, *code));
+    /// Builds the concatenation of the characters of `literal`, numbered from `1`. The empty
+    /// string gives [Node::Epsilon].
+    pub fn from_str_literal(literal: &str) -> Node {
+        let mut terminals = (1..)
+            .zip(literal.chars())
+            .map(|(code, c)| Node::Terminal(c, code));
+        let Some(first) = terminals.next() else {
+            return Node::Epsilon;
+        };
+        terminals.fold(first, |left, right| {
+            Node::Operation(Operator::Concat, Box::new(left), Some(Box::new(right)))
+        })
+    }
+
+    /// Builds the concatenation of `count` copies of the terminal `symbol`, numbered from `1`. A
+    /// count of zero gives [Node::Epsilon].
+    pub fn repeat(symbol: char, count: u32) -> Node {
+        if count == 0 {
+            return Node::Epsilon;
         }
-        Node::Operation(op, left, right) => match op {
-            Operator::Or => {
-                let left_set = prefix_set(left);
-                let right_set = prefix_set(right.as_ref().unwrap());
-                set.extend(left_set);
-                set.extend(right_set);
+        (2..=count).fold(Node::Terminal(symbol, 1), |left, code| {
+            Node::Operation(
+                Operator::Concat,
+                Box::new(left),
+                Some(Box::new(Node::Terminal(symbol, code))),
+            )
+        })
+    }
+
+    /// Encodes the tree in a compact binary format, read back by [Node::from_bytes].
+    ///
+    /// The encoding is the number of nodes as a little-endian `u32`, followed by the nodes in
+    /// post-order. Every node is a tag byte: an epsilon has nothing more, a terminal has its
+    /// character and code as little-endian `u32`s, and an operation has its operator byte.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // Reversing a pre-order walk visiting the right operand first gives the post-order
+        let mut nodes = Vec::new();
+        let mut pending = vec![self];
+        while let Some(node) = pending.pop() {
+            nodes.push(node);
+            if let Node::Operation(_, left, right) = node {
+                pending.push(left);
+                if let Some(right) = right {
+                    pending.push(right);
+                }
             }
-            Operator::Concat => {
-                let left_set = prefix_set(left);
-                set.extend(left_set);
-                let right_set = prefix_set(right.as_ref().unwrap());
-                let nullable_set = nullability_set(left);
-
-                // If the left expression is nullable, include the first set of the right expression
-                if nullable_set.contains(&SetTerminal::Epsilon) {
-                    set.extend(right_set);
+        }
+
+        let mut bytes = Vec::with_capacity(4 + nodes.len() * 9);
+        bytes.extend((nodes.len() as u32).to_le_bytes());
+        for node in nodes.into_iter().rev() {
+            match node {
+                Node::Epsilon => bytes.push(EPSILON_TAG),
+                Node::Terminal(symbol, code) => {
+                    bytes.push(TERMINAL_TAG);
+                    bytes.extend((*symbol as u32).to_le_bytes());
+                    bytes.extend(code.to_le_bytes());
+                }
+                Node::Operation(op, _, right) => {
+                    bytes.push(if right.is_some() {
+                        BINARY_TAG
+                    } else {
+                        UNARY_TAG
+                    });
+                    bytes.push(OPERATORS.iter().position(|o| o == op).unwrap() as u8);
                 }
             }
-            Operator::Production => {
-                let left_set = prefix_set(left);
-                set = left_set;
+        }
+        bytes
+    }
+
+    /// Decodes a tree encoded by [Node::to_bytes].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Node, DecodeError> {
+        let mut rest = bytes;
+        let mut take = |len: usize| {
+            if rest.len() < len {
+                return Err(DecodeError::UnexpectedEnd);
             }
-            _ => todo!(),
-        },
+            let (taken, remaining) = rest.split_at(len);
+            rest = remaining;
+            Ok(taken)
+        };
+        let read_u32 = |bytes: &[u8]| u32::from_le_bytes(bytes.try_into().unwrap());
+
+        let count = read_u32(take(4)?);
+        let mut stack: Vec<Node> = Vec::new();
+        for _ in 0..count {
+            let tag = take(1)?[0];
+            let node = match tag {
+                EPSILON_TAG => Node::Epsilon,
+                TERMINAL_TAG => {
+                    let value = read_u32(take(4)?);
+                    let symbol = char::from_u32(value).ok_or(DecodeError::InvalidChar(value))?;
+                    Node::Terminal(symbol, read_u32(take(4)?))
+                }
+                UNARY_TAG | BINARY_TAG => {
+                    let op_byte = take(1)?[0];
+                    let op = *OPERATORS
+                        .get(op_byte as usize)
+                        .ok_or(DecodeError::InvalidOperator(op_byte))?;
+                    let right = if tag == BINARY_TAG {
+                        Some(Box::new(stack.pop().ok_or(DecodeError::Malformed)?))
+                    } else {
+                        None
+                    };
+                    let left = stack.pop().ok_or(DecodeError::Malformed)?;
+                    Node::Operation(op, Box::new(left), right)
+                }
+                _ => return Err(DecodeError::InvalidTag(tag)),
+            };
+            stack.push(node);
+        }
+
+        if !rest.is_empty() {
+            return Err(DecodeError::TrailingBytes);
+        }
+        match (stack.pop(), stack.is_empty()) {
+            (Some(tree), true) => Ok(tree),
+            _ => Err(DecodeError::Malformed),
+        }
     }
-    set
 }
 
-/// The `suffix_set` function returns the set of [SetTerminal] that are suffixes of a regular expression tree.
-pub fn suffix_set(regex_tree: &Node) -> HashSet<SetTerminal> {
-    let mut set = HashSet::new();
-    match regex_tree {
-        Node::Terminal(symbol, code) => {
-            set.insert(SetTerminal::SingleElement(*symbol, *code));
+impl<S: Symbol> Node<S> {
+    /// Returns true if both trees are equal when ignoring the terminal codes, which only depend
+    /// on how the trees were built. The derived `PartialEq` compares the codes too.
+    pub fn structurally_eq(&self, other: &Node<S>) -> bool {
+        match (self, other) {
+            (Node::Terminal(a, _), Node::Terminal(b, _)) => a == b,
+            (Node::Epsilon, Node::Epsilon) => true,
+            (
+                Node::Operation(op, left, right),
+                Node::Operation(other_op, other_left, other_right),
+            ) => {
+                op == other_op
+                    && left.structurally_eq(other_left)
+                    && match (right, other_right) {
+                        (Some(right), Some(other_right)) => right.structurally_eq(other_right),
+                        (None, None) => true,
+                        _ => false,
+                    }
+            }
+            _ => false,
         }
-        Node::Operation(op, left, right) => match op {
-            Operator::Or => {
-                let left_set = suffix_set(left);
-                let right_set = suffix_set(right.as_ref().unwrap());
-                set.extend(left_set);
-                set.extend(right_set);
+    }
+}
+
+impl<S> Node<S> {
+    /// Returns the number of nodes on the longest path from the root to a leaf, a leaf alone having depth `1`.
+    pub fn depth(&self) -> usize {
+        self.fold(&mut |_| 1, &mut |_, left, right| {
+            1 + left.max(right.unwrap_or(0))
+        })
+    }
+
+    /// Folds the tree bottom-up: every leaf, terminal or epsilon, is mapped with `leaf`, and every
+    /// operation is combined from the folded values of its operands with `operation`.
+    ///
+    /// The leaves are mapped from left to right. The tree is walked with an explicit stack, so
+    /// deep trees do not overflow the call stack.
+    pub fn fold<T, F, G>(&self, leaf: &mut F, operation: &mut G) -> T
+    where
+        F: FnMut(&Node<S>) -> T,
+        G: FnMut(&Operator, T, Option<T>) -> T,
+    {
+        enum Step<'a, S> {
+            Visit(&'a Node<S>),
+            Combine(&'a Operator, bool),
+        }
+        let mut steps = vec![Step::Visit(self)];
+        let mut values = Vec::new();
+        while let Some(step) = steps.pop() {
+            match step {
+                Step::Visit(node) => match node {
+                    Node::Terminal(_, _) | Node::Epsilon => values.push(leaf(node)),
+                    Node::Operation(op, left, right) => {
+                        steps.push(Step::Combine(op, right.is_some()));
+                        if let Some(right) = right {
+                            steps.push(Step::Visit(right));
+                        }
+                        steps.push(Step::Visit(left));
+                    }
+                },
+                Step::Combine(op, binary) => {
+                    let right = if binary { values.pop() } else { None };
+                    let left = values
+                        .pop()
+                        .expect("an operand is folded before its operation");
+                    values.push(operation(op, left, right));
+                }
             }
-            Operator::Concat => {
-                let left_set = suffix_set(right.as_ref().unwrap());
-                set.extend(left_set);
-                let right_set = suffix_set(left);
-                let nullable_set = nullability_set(right.as_ref().unwrap());
-
-                // If the left expression is nullable, include the first set of the right expression
-                if nullable_set.contains(&SetTerminal::Epsilon) {
-                    set.extend(right_set);
+        }
+        values.pop().expect("the root is folded last")
+    }
+
+    /// Renumbers the terminals of the tree from `1` in left to right order.
+    ///
+    /// The codes of a tree built by the macros depend on the order in which they were expanded, so
+    /// renumbering gives each tree its own deterministic codes. Code `0` is never used, being the start state of the NFA.
+    pub fn renumber(&mut self) {
+        let mut code = 0;
+        let mut pending = vec![self];
+        while let Some(node) = pending.pop() {
+            match node {
+                Node::Terminal(_, terminal_code) => {
+                    code += 1;
+                    *terminal_code = code;
+                }
+                Node::Epsilon => {}
+                Node::Operation(_, left, right) => {
+                    if let Some(right) = right {
+                        pending.push(right);
+                    }
+                    pending.push(left);
                 }
             }
-            Operator::Production => {
-                let left_set = suffix_set(left);
-                set = left_set;
+        }
+    }
+}
+
+impl<S: Clone> Clone for Node<S> {
+    /// Clones the tree with [Node::fold], as the derived clone would recurse once per level.
+    fn clone(&self) -> Self {
+        self.fold(
+            &mut |leaf| match leaf {
+                Node::Terminal(symbol, code) => Node::Terminal(symbol.clone(), *code),
+                _ => Node::Epsilon,
+            },
+            &mut |op, left, right| Node::Operation(*op, Box::new(left), right.map(Box::new)),
+        )
+    }
+}
+
+impl<S> Drop for Node<S> {
+    /// Drops the operands with an explicit stack, as the derived drop would recurse once per level.
+    fn drop(&mut self) {
+        let mut pending = Vec::new();
+        if let Node::Operation(_, left, right) = self {
+            take_operands(left, right, &mut pending);
+        }
+        while let Some(mut node) = pending.pop() {
+            if let Node::Operation(_, left, right) = &mut node {
+                take_operands(left, right, &mut pending);
+            }
+        }
+    }
+}
+
+/// Moves the operations among `left` and `right` to `pending`, leaving epsilons in their place.
+fn take_operands<S>(
+    left: &mut Node<S>,
+    right: &mut Option<Box<Node<S>>>,
+    pending: &mut Vec<Node<S>>,
+) {
+    for operand in std::iter::once(left).chain(right.as_deref_mut()) {
+        if let Node::Operation(_, _, _) = operand {
+            pending.push(std::mem::replace(operand, Node::Epsilon));
+        }
+    }
+}
+
+/// The `Alternation` struct collects nodes into their alternation, like the `or!` macro does for
+/// a fixed list of operands.
+///
+/// ```
+/// use gregex_logic::translation::node::{Alternation, Node};
+///
+/// let words = ["ab", "cd"].map(Node::from_str_literal);
+/// let tree = words.into_iter().collect::<Alternation>().into_node().unwrap();
+/// assert!(gregex_logic::regex(&tree).run("cd"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alternation<S = char> {
+    node: Option<Node<S>>,
+}
+
+/// The `Concatenation` struct collects nodes into their concatenation, like the `dot!` macro does
+/// for a fixed list of operands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Concatenation<S = char> {
+    node: Option<Node<S>>,
+}
+
+/// Returns the nodes combined with the binary operator `op` into a balanced tree, keeping their
+/// order, or `None` if there are none.
+///
+/// The depth of the tree only grows with the logarithm of the number of nodes, unlike the chain
+/// built by folding them one by one.
+pub(crate) fn balanced<S>(op: Operator, mut level: Vec<Node<S>>) -> Option<Node<S>> {
+    while level.len() > 1 {
+        let mut pairs = Vec::with_capacity(level.len().div_ceil(2));
+        let mut nodes = level.into_iter();
+        while let Some(left) = nodes.next() {
+            pairs.push(match nodes.next() {
+                Some(right) => Node::Operation(op, Box::new(left), Some(Box::new(right))),
+                None => left,
+            });
+        }
+        level = pairs;
+    }
+    level.pop()
+}
+
+/// Folds `nodes` into `accumulated` as the left operands of `op`.
+fn accumulate<S>(
+    accumulated: &mut Option<Node<S>>,
+    op: Operator,
+    nodes: impl Iterator<Item = Node<S>>,
+) {
+    for node in nodes {
+        *accumulated = Some(match accumulated.take() {
+            Some(left) => Node::Operation(op, Box::new(left), Some(Box::new(node))),
+            None => node,
+        });
+    }
+}
+
+impl<S> Default for Alternation<S> {
+    fn default() -> Self {
+        Self { node: None }
+    }
+}
+
+impl<S: Symbol> Alternation<S> {
+    /// Creates an alternation of no nodes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the alternation of the collected nodes, with the terminals renumbered, see [Node::renumber].
+    ///
+    /// No node gives `None`, as there is no node for the empty language, see
+    /// [crate::nfa::NFA::never] for the NFA matching nothing.
+    pub fn into_node(self) -> Option<Node<S>> {
+        let mut node = self.node?;
+        node.renumber();
+        Some(node)
+    }
+}
+
+impl<S> Extend<Node<S>> for Alternation<S> {
+    fn extend<I: IntoIterator<Item = Node<S>>>(&mut self, nodes: I) {
+        accumulate(&mut self.node, Operator::Or, nodes.into_iter());
+    }
+}
+
+impl<S> FromIterator<Node<S>> for Alternation<S> {
+    fn from_iter<I: IntoIterator<Item = Node<S>>>(nodes: I) -> Self {
+        let mut alternation = Self::default();
+        alternation.extend(nodes);
+        alternation
+    }
+}
+
+impl<S> Default for Concatenation<S> {
+    fn default() -> Self {
+        Self { node: None }
+    }
+}
+
+impl<S: Symbol> Concatenation<S> {
+    /// Creates a concatenation of no nodes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the concatenation of the collected nodes, with the terminals renumbered, see
+    /// [Node::renumber]. No node gives [Node::Epsilon].
+    pub fn into_node(self) -> Node<S> {
+        let mut node = self.node.unwrap_or(Node::Epsilon);
+        node.renumber();
+        node
+    }
+}
+
+impl<S> Extend<Node<S>> for Concatenation<S> {
+    fn extend<I: IntoIterator<Item = Node<S>>>(&mut self, nodes: I) {
+        accumulate(&mut self.node, Operator::Concat, nodes.into_iter());
+    }
+}
+
+impl<S> FromIterator<Node<S>> for Concatenation<S> {
+    fn from_iter<I: IntoIterator<Item = Node<S>>>(nodes: I) -> Self {
+        let mut concatenation = Self::default();
+        concatenation.extend(nodes);
+        concatenation
+    }
+}
+
+/// The `nullability_set` function returns the set of [SetTerminal] that are nullable in a regular expression tree.
+pub fn nullability_set<S: Symbol>(regex_tree: &Node<S>) -> HashSet<SetTerminal<S>> {
+    regex_tree.fold(
+        &mut |leaf| match leaf {
+            Node::Epsilon => HashSet::from([SetTerminal::Epsilon]),
+            _ => HashSet::from([SetTerminal::Empty]),
+        },
+        &mut |op, left, right| match op {
+            Operator::Or => union(left, second_operand(right)),
+            Operator::Concat => {
+                // A concatenation is only nullable if all of its expressions are
+                let nullable = left.contains(&SetTerminal::Epsilon)
+                    && second_operand(right).contains(&SetTerminal::Epsilon);
+                HashSet::from([if nullable {
+                    SetTerminal::Epsilon
+                } else {
+                    SetTerminal::Empty
+                }])
             }
-            _ => todo!(),
+            Operator::Production | Operator::Question => HashSet::from([SetTerminal::Epsilon]),
+            Operator::Plus => left,
         },
+    )
+}
+
+/// Returns true if the regular expression tree matches the empty string.
+pub(crate) fn is_nullable<S: Symbol>(regex_tree: &Node<S>) -> bool {
+    nullability_set(regex_tree).contains(&SetTerminal::Epsilon)
+}
+
+/// Returns the right operand of a binary operation.
+///
+/// # Panics
+///
+/// Panics if the operation has no right operand, see [crate::try_compile].
+fn second_operand<T>(right: Option<T>) -> T {
+    right.expect("a binary operation has two operands")
+}
+
+/// Returns the union of both sets, reusing the larger one rather than copying both.
+fn union<S: Symbol>(
+    left: HashSet<SetTerminal<S>>,
+    right: HashSet<SetTerminal<S>>,
+) -> HashSet<SetTerminal<S>> {
+    let (mut larger, smaller) = if left.len() >= right.len() {
+        (left, right)
+    } else {
+        (right, left)
+    };
+    larger.extend(smaller);
+    larger
+}
+
+/// Returns the products of every element of `suffix` with every element of `prefix`.
+fn products<S: Symbol>(
+    suffix: &HashSet<SetTerminal<S>>,
+    prefix: &HashSet<SetTerminal<S>>,
+) -> HashSet<SetTerminal<S>> {
+    let mut set = HashSet::with_capacity(suffix.len() * prefix.len());
+    for i in suffix {
+        for j in prefix {
+            set.insert(i.product(j));
+        }
     }
     set
 }
 
-/// The `factors_set` function returns the set of [SetTerminal] that are factors of a regular expression tree.
-/// 
-/// Factors in this scenario mean the set of terminals that can be produced by the regular expression.
-pub fn factors_set(regex_tree: &Node) -> HashSet<SetTerminal> {
-    let mut set = HashSet::new();
-    match regex_tree {
-        Node::Terminal(_, _) => {
-            set.insert(SetTerminal::Empty);
-        }
-        Node::Operation(op, left, right) => match op {
+/// The sets of a subtree the sets of its parent are built from.
+struct Sets<S> {
+    nullable: bool,
+    prefix: HashSet<SetTerminal<S>>,
+    suffix: HashSet<SetTerminal<S>>,
+    /// Left empty unless the factors were asked for, as their products are the costly part.
+    factors: HashSet<SetTerminal<S>>,
+}
+
+/// Folds the prefix, suffix and, if `with_factors` is true, factors sets of the tree bottom-up.
+///
+/// The set of an operation only depends on the sets of its operands, so [Node::fold] computes
+/// them in one pass without recursion.
+fn tree_sets<S: Symbol>(regex_tree: &Node<S>, with_factors: bool) -> Sets<S> {
+    regex_tree.fold(
+        &mut |leaf| {
+            let ends = || match leaf {
+                Node::Terminal(symbol, code) => {
+                    HashSet::from([SetTerminal::SingleElement(symbol.clone(), *code)])
+                }
+                _ => HashSet::new(),
+            };
+            Sets {
+                nullable: matches!(leaf, Node::Epsilon),
+                prefix: ends(),
+                suffix: ends(),
+                factors: HashSet::from([SetTerminal::Empty]),
+            }
+        },
+        &mut |op, left, right| match op {
             Operator::Or => {
-                let left_set = factors_set(left);
-                let right_set = factors_set(right.as_ref().unwrap());
-                set.extend(left_set);
-                set.extend(right_set);
+                let right = second_operand(right);
+                Sets {
+                    nullable: left.nullable || right.nullable,
+                    prefix: union(left.prefix, right.prefix),
+                    suffix: union(left.suffix, right.suffix),
+                    factors: union(left.factors, right.factors),
+                }
             }
             Operator::Concat => {
-                let left_set = factors_set(left);
-                let right_set = factors_set(right.as_ref().unwrap());
-                let suffix_set = suffix_set(left);
-                let prefix_set = prefix_set(right.as_ref().unwrap());
-                set.extend(left_set);
-                set.extend(right_set);
-                for i in suffix_set {
-                    for j in &prefix_set {
-                        set.insert(i.product(j));
-                    }
+                let right = second_operand(right);
+                let mut factors = union(left.factors, right.factors);
+                if with_factors {
+                    factors.extend(products(&left.suffix, &right.prefix));
+                }
+                Sets {
+                    nullable: left.nullable && right.nullable,
+                    // The prefixes of the right expression only start the concatenation if the
+                    // left one can be skipped, and the other way around for the suffixes
+                    prefix: if left.nullable {
+                        union(left.prefix, right.prefix)
+                    } else {
+                        left.prefix
+                    },
+                    suffix: if right.nullable {
+                        union(left.suffix, right.suffix)
+                    } else {
+                        right.suffix
+                    },
+                    factors,
                 }
             }
-            Operator::Production => {
-                let left_set = factors_set(left);
-                let suffix_set = suffix_set(left);
-                let prefix_set = prefix_set(left);
-                set.extend(left_set);
-
-                for i in suffix_set {
-                    for j in &prefix_set {
-                        set.insert(i.product(j));
-                    }
+            Operator::Production | Operator::Plus | Operator::Question => {
+                let mut factors = left.factors;
+                if with_factors && *op != Operator::Question {
+                    factors.extend(products(&left.suffix, &left.prefix));
+                }
+                Sets {
+                    nullable: left.nullable || *op != Operator::Plus,
+                    prefix: left.prefix,
+                    suffix: left.suffix,
+                    factors,
                 }
             }
-            _ => todo!(),
         },
-    }
+    )
+}
+
+/// Returns an upper bound of the number of set operations the set functions do on the tree.
+///
+/// Every node costs one operation, and the factors of every concatenation and star cost the size
+/// of the products of their suffix and prefix sets, bounded by the numbers of terminals.
+pub(crate) fn construction_cost<S: Symbol>(regex_tree: &Node<S>) -> u64 {
+    let (cost, _) = regex_tree.fold(
+        &mut |leaf| match leaf {
+            Node::Terminal(_, _) => (1u64, 1u64),
+            _ => (1, 0),
+        },
+        &mut |op, (left_cost, left_terminals), right| {
+            let (right_cost, right_terminals) = right.unwrap_or((0, 0));
+            let terminals = left_terminals.saturating_add(right_terminals);
+            let products = match op {
+                Operator::Concat => left_terminals.saturating_mul(right_terminals),
+                Operator::Production | Operator::Plus => terminals.saturating_mul(terminals),
+                Operator::Or | Operator::Question => 0,
+            };
+            let cost = left_cost
+                .saturating_add(right_cost)
+                .saturating_add(products)
+                .saturating_add(1);
+            (cost, terminals)
+        },
+    );
+    cost
+}
+
+/// The `prefix_set` function returns the set of [SetTerminal] that are prefixes of a regular expression tree.
+pub fn prefix_set<S: Symbol>(regex_tree: &Node<S>) -> HashSet<SetTerminal<S>> {
+    tree_sets(regex_tree, false).prefix
+}
 
+/// The `suffix_set` function returns the set of [SetTerminal] that are suffixes of a regular expression tree.
+pub fn suffix_set<S: Symbol>(regex_tree: &Node<S>) -> HashSet<SetTerminal<S>> {
+    tree_sets(regex_tree, false).suffix
+}
+
+/// The `factors_set` function returns the set of [SetTerminal] that are factors of a regular expression tree.
+///
+/// Factors in this scenario mean the set of terminals that can be produced by the regular expression.
+pub fn factors_set<S: Symbol>(regex_tree: &Node<S>) -> HashSet<SetTerminal<S>> {
+    let mut set = factors_set_raw(regex_tree);
     if set.contains(&SetTerminal::Empty) && set.len() > 1 {
         set.remove(&SetTerminal::Empty);
     }
     set
 }
 
+/// Returns the factors of a regular expression tree like [factors_set], but keeps the
+/// [SetTerminal::Empty] element its leaves contribute even when there are other factors.
+///
+/// The construction has no use for this element, but it shows how the sets of the leaves combine.
+pub fn factors_set_raw<S: Symbol>(regex_tree: &Node<S>) -> HashSet<SetTerminal<S>> {
+    tree_sets(regex_tree, true).factors
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -192,6 +756,204 @@ mod tests {
         assert_eq!(set, test_set);
     }
 
+    #[test]
+    fn nullability_set_test_concat_nullable() {
+        let star = |symbol, code| {
+            Box::new(Node::Operation(
+                Operator::Production,
+                Box::new(Node::Terminal(symbol, code)),
+                None,
+            ))
+        };
+
+        let tree = Node::Operation(
+            Operator::Concat,
+            star('a', 1),
+            Some(Box::new(Node::Terminal('b', 2))),
+        );
+        let mut test_set = HashSet::new();
+        test_set.insert(SetTerminal::Empty);
+        assert_eq!(nullability_set(&tree), test_set);
+
+        let tree = Node::Operation(Operator::Concat, star('a', 1), Some(star('b', 2)));
+        let mut test_set = HashSet::new();
+        test_set.insert(SetTerminal::Epsilon);
+        assert_eq!(nullability_set(&tree), test_set);
+    }
+
+    #[test]
+    fn visit_test() {
+        struct TerminalCollector(Vec<(char, u32)>);
+
+        impl NodeVisitor for TerminalCollector {
+            fn visit_terminal(&mut self, symbol: char, code: u32) {
+                self.0.push((symbol, code));
+            }
+        }
+
+        // (a|b)*c
+        let tree = Node::Operation(
+            Operator::Concat,
+            Box::new(Node::Operation(
+                Operator::Production,
+                Box::new(Node::Operation(
+                    Operator::Or,
+                    Box::new(Node::Terminal('a', 1)),
+                    Some(Box::new(Node::Terminal('b', 2))),
+                )),
+                None,
+            )),
+            Some(Box::new(Node::Terminal('c', 3))),
+        );
+
+        let mut collector = TerminalCollector(Vec::new());
+        tree.visit(&mut collector);
+        assert_eq!(collector.0, vec![('a', 1), ('b', 2), ('c', 3)]);
+
+        let depth = tree.fold(&mut |_| 1, &mut |_, left, right: Option<usize>| {
+            1 + left.max(right.unwrap_or(0))
+        });
+        assert_eq!(depth, 4);
+    }
+
+    #[test]
+    fn from_str_literal_test() {
+        let nfa = crate::regex(&Node::from_str_literal("abc"));
+        assert!(nfa.run("abc"));
+        assert!(!nfa.run("ab"));
+        assert!(!nfa.run("abcc"));
+
+        assert_eq!(Node::from_str_literal(""), Node::Epsilon);
+        let nfa = crate::regex(&Node::from_str_literal(""));
+        assert!(nfa.run(""));
+        assert!(!nfa.run("a"));
+    }
+
+    #[test]
+    fn repeat_test() {
+        // Long enough to overflow the stack of a test thread if a pass recursed once per terminal
+        let nfa = crate::regex(&Node::repeat('a', 50_000));
+        assert!(nfa.run("a".repeat(50_000)));
+        assert!(!nfa.run("a".repeat(49_999)));
+        assert!(!nfa.run("a".repeat(50_001)));
+
+        assert_eq!(Node::repeat('a', 0), Node::Epsilon);
+        let nfa = crate::regex(&Node::repeat('a', 0));
+        assert!(nfa.run(""));
+        assert!(!nfa.run("a"));
+    }
+
+    #[test]
+    fn bytes_round_trip_test() {
+        let trees = [
+            Node::Epsilon,
+            Node::Terminal('é', 3),
+            Node::from_str_literal("abc"),
+            Node::repeat('a', 5000),
+            Node::Operation(
+                Operator::Or,
+                Box::new(Node::Operation(
+                    Operator::Production,
+                    Box::new(Node::Terminal('a', 1)),
+                    None,
+                )),
+                Some(Box::new(Node::Operation(
+                    Operator::Question,
+                    Box::new(Node::Epsilon),
+                    None,
+                ))),
+            ),
+        ];
+        for tree in &trees {
+            assert_eq!(Node::from_bytes(&tree.to_bytes()).as_ref(), Ok(tree));
+        }
+    }
+
+    #[test]
+    fn from_bytes_error_test() {
+        let bytes = Node::from_str_literal("ab").to_bytes();
+        assert_eq!(
+            Node::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(DecodeError::UnexpectedEnd)
+        );
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(Node::from_bytes(&trailing), Err(DecodeError::TrailingBytes));
+        assert_eq!(
+            Node::from_bytes(&[1, 0, 0, 0, 7]),
+            Err(DecodeError::InvalidTag(7))
+        );
+        assert_eq!(
+            Node::from_bytes(&[1, 0, 0, 0, 2, 2]),
+            Err(DecodeError::Malformed)
+        );
+        assert_eq!(Node::from_bytes(&[0, 0, 0, 0]), Err(DecodeError::Malformed));
+        assert_eq!(
+            Node::from_bytes(&[1, 0, 0, 0, 1, 0, 0xd8, 0, 0, 1, 0, 0, 0]),
+            Err(DecodeError::InvalidChar(0xd800))
+        );
+    }
+
+    #[test]
+    fn structurally_eq_test() {
+        let tree = Node::Operation(
+            Operator::Or,
+            Box::new(Node::Terminal('a', 4)),
+            Some(Box::new(Node::Terminal('b', 9))),
+        );
+        let renumbered = Node::Operation(
+            Operator::Or,
+            Box::new(Node::Terminal('a', 1)),
+            Some(Box::new(Node::Terminal('b', 2))),
+        );
+        assert_ne!(tree, renumbered);
+        assert!(tree.structurally_eq(&renumbered));
+        assert!(!tree.structurally_eq(&Node::from_str_literal("ab")));
+        assert!(!tree.structurally_eq(&Node::Terminal('a', 4)));
+    }
+
+    #[test]
+    fn depth_test() {
+        assert_eq!(Node::from_str_literal("").depth(), 1);
+        assert_eq!(Node::from_str_literal("abc").depth(), 3);
+        let tree = Node::Operation(
+            Operator::Or,
+            Box::new(Node::Terminal('a', 1)),
+            Some(Box::new(Node::Operation(
+                Operator::Production,
+                Box::new(Node::Terminal('b', 2)),
+                None,
+            ))),
+        );
+        assert_eq!(tree.depth(), 3);
+    }
+
+    #[test]
+    fn renumber_test() {
+        let mut tree = Node::Operation(
+            Operator::Concat,
+            Box::new(Node::Operation(
+                Operator::Production,
+                Box::new(Node::Terminal('a', 7)),
+                None,
+            )),
+            Some(Box::new(Node::Terminal('b', 7))),
+        );
+        tree.renumber();
+        assert_eq!(
+            tree,
+            Node::Operation(
+                Operator::Concat,
+                Box::new(Node::Operation(
+                    Operator::Production,
+                    Box::new(Node::Terminal('a', 1)),
+                    None
+                )),
+                Some(Box::new(Node::Terminal('b', 2))),
+            )
+        );
+    }
+
     #[test]
     fn nullability_set_test_production() {
         let tree = Node::Operation(Operator::Production, Box::new(Node::Terminal('a', 1)), None);
@@ -429,6 +1191,123 @@ mod tests {
         assert_eq!(set, test_set);
     }
 
+    #[test]
+    fn plus_question_sets_test() {
+        // Linearized regex: (a1b2)+c3?
+        let tree = Node::Operation(
+            Operator::Concat,
+            Box::new(Node::Operation(
+                Operator::Plus,
+                Box::new(Node::Operation(
+                    Operator::Concat,
+                    Box::new(Node::Terminal('a', 1)),
+                    Some(Box::new(Node::Terminal('b', 2))),
+                )),
+                None,
+            )),
+            Some(Box::new(Node::Operation(
+                Operator::Question,
+                Box::new(Node::Terminal('c', 3)),
+                None,
+            ))),
+        );
+        assert!(!is_nullable(&tree));
+        assert_eq!(
+            prefix_set(&tree),
+            HashSet::from([SetTerminal::SingleElement('a', 1)])
+        );
+        assert_eq!(
+            suffix_set(&tree),
+            HashSet::from([
+                SetTerminal::SingleElement('b', 2),
+                SetTerminal::SingleElement('c', 3),
+            ])
+        );
+        assert_eq!(
+            factors_set(&tree),
+            HashSet::from([
+                SetTerminal::DoubleElement('a', 1, 'b', 2),
+                SetTerminal::DoubleElement('b', 2, 'a', 1),
+                SetTerminal::DoubleElement('b', 2, 'c', 3),
+            ])
+        );
+    }
+
+    #[test]
+    fn linearized_test() {
+        let tree = crate::parser::parse("(a(ab)*)*|(ba)*").unwrap();
+        assert_eq!(tree.linearized(), "(a1(a2b3)*)*|(b4a5)*");
+        let tree = crate::parser::parse("(a|b)c+d?").unwrap();
+        assert_eq!(tree.linearized(), "(a1|b2)c3+d4?");
+        assert_eq!(Node::from_str_literal("").linearized(), "ε");
+    }
+
+    #[test]
+    fn accumulators_test() {
+        let words: Vec<Node> = ["ab", "c", ""].map(Node::from_str_literal).to_vec();
+        let nfa = crate::regex(
+            &words
+                .clone()
+                .into_iter()
+                .collect::<Alternation>()
+                .into_node()
+                .unwrap(),
+        );
+        for (input, expected) in [("ab", true), ("c", true), ("", true), ("abc", false)] {
+            assert_eq!(nfa.run(input), expected, "{:?}", input);
+        }
+
+        let mut concatenation: Concatenation = words.into_iter().collect();
+        concatenation.extend([Node::Operation(
+            Operator::Production,
+            Box::new(Node::Terminal('d', 0)),
+            None,
+        )]);
+        let tree = concatenation.into_node();
+        assert_eq!(tree.linearized(), "a1b2c3εd4*");
+        let nfa = crate::regex(&tree);
+        assert!(nfa.run("abc"));
+        assert!(nfa.run("abcddd"));
+        assert!(!nfa.run("ab"));
+
+        assert_eq!(Concatenation::<char>::new().into_node(), Node::Epsilon);
+    }
+
+    #[test]
+    fn empty_alternation_test() {
+        assert_eq!(Alternation::<char>::new().into_node(), None);
+        assert_eq!(
+            Alternation::from_iter([Node::Terminal('a', 7)]).into_node(),
+            Some(Node::Terminal('a', 1))
+        );
+    }
+
+    #[test]
+    fn factors_set_raw_test() {
+        let terminal = Node::Terminal('a', 1);
+        assert_eq!(
+            factors_set_raw(&terminal),
+            HashSet::from([SetTerminal::Empty])
+        );
+
+        let tree = Node::Operation(
+            Operator::Concat,
+            Box::new(Node::Terminal('a', 1)),
+            Some(Box::new(Node::Terminal('b', 2))),
+        );
+        assert_eq!(
+            factors_set_raw(&tree),
+            HashSet::from([
+                SetTerminal::Empty,
+                SetTerminal::DoubleElement('a', 1, 'b', 2),
+            ])
+        );
+        assert_eq!(
+            factors_set(&tree),
+            HashSet::from([SetTerminal::DoubleElement('a', 1, 'b', 2)])
+        );
+    }
+
     #[test]
     fn factors_set_test_complete() {
         // Linearized regex: (a(ab)*)* + (ba)*
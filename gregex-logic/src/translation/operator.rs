@@ -1,11 +1,55 @@
 //! Contains the `Operator` enum.
 
 /// The `Operator` enum represents the different operations that can be performed on a regular expression.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Operator {
     Or,
     Concat,
     Production,
     Plus,
     Question,
-}
\ No newline at end of file
+}
+
+impl Operator {
+    /// Returns how tightly the operator binds its operands, higher binding tighter: the postfix
+    /// operators bind tighter than concatenation, which binds tighter than alternation.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Operator::Or => 1,
+            Operator::Concat => 2,
+            Operator::Production | Operator::Plus | Operator::Question => 3,
+        }
+    }
+
+    /// Returns true if the operator takes two operands, the right operand of its
+    /// [crate::translation::node::Node::Operation] being `Some`.
+    pub fn is_binary(&self) -> bool {
+        matches!(self, Operator::Or | Operator::Concat)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copy_test() {
+        let op = Operator::Production;
+        let copy = op;
+        assert_eq!(op, copy);
+        assert_ne!(copy, Operator::Or);
+    }
+
+    #[test]
+    fn precedence_test() {
+        // `ab|c*` reads as `(ab)|(c*)`
+        assert!(Operator::Production.precedence() > Operator::Concat.precedence());
+        assert!(Operator::Concat.precedence() > Operator::Or.precedence());
+        assert_eq!(Operator::Plus.precedence(), Operator::Question.precedence());
+
+        assert!(Operator::Or.is_binary());
+        assert!(Operator::Concat.is_binary());
+        assert!(!Operator::Production.is_binary());
+        assert!(!Operator::Question.is_binary());
+    }
+}
@@ -1,38 +1,50 @@
 //! Contains the `SetTerminal` enum, which represents the different types of terminals that can be used in a regular expression.
 
+use crate::symbol::Symbol;
 use std::hash::{Hash, Hasher};
 
 /// The `SetTerminal` enum represents the different types of terminals that can be used in a regular expression.
 #[derive(Debug)]
-pub enum SetTerminal {
-    SingleElement(char, u32),            // a₁
-    DoubleElement(char, u32, char, u32), // a₁b₂
-    Epsilon,                             // ε
-    Empty,                               // ∅
+pub enum SetTerminal<S = char> {
+    SingleElement(S, u32),         // a₁
+    DoubleElement(S, u32, S, u32), // a₁b₂
+    Epsilon,                       // ε
+    Empty,                         // ∅
 }
 
-impl SetTerminal {
+impl<S: Symbol> SetTerminal<S> {
     /// Performs the cartesian product of two `SetTerminal` instances.
-    pub fn product(&self, other: &SetTerminal) -> SetTerminal {
-        match (self, other) {
+    ///
+    /// # Panics
+    ///
+    /// Panics if the product is not defined, like for a [SetTerminal::DoubleElement], see [SetTerminal::try_product].
+    pub fn product(&self, other: &SetTerminal<S>) -> SetTerminal<S> {
+        self.try_product(other).expect("Invalid product")
+    }
+
+    /// Performs the cartesian product of two `SetTerminal` instances, returning `None` if it is not
+    /// defined, when one of them is a [SetTerminal::DoubleElement] and the other is not empty.
+    pub fn try_product(&self, other: &SetTerminal<S>) -> Option<SetTerminal<S>> {
+        let product = match (self, other) {
             (SetTerminal::SingleElement(a, a_code), SetTerminal::SingleElement(b, b_code)) => {
-                SetTerminal::DoubleElement(*a, *a_code, *b, *b_code)
+                SetTerminal::DoubleElement(a.clone(), *a_code, b.clone(), *b_code)
             }
             (SetTerminal::SingleElement(a, a_code), SetTerminal::Epsilon) => {
-                SetTerminal::SingleElement(*a, *a_code)
+                SetTerminal::SingleElement(a.clone(), *a_code)
             }
             (SetTerminal::Epsilon, SetTerminal::SingleElement(b, b_code)) => {
-                SetTerminal::SingleElement(*b, *b_code)
+                SetTerminal::SingleElement(b.clone(), *b_code)
             }
             (SetTerminal::Epsilon, SetTerminal::Epsilon) => SetTerminal::Epsilon,
             (SetTerminal::Empty, _) => SetTerminal::Empty,
             (_, SetTerminal::Empty) => SetTerminal::Empty,
-            _ => unreachable!("Invalid product"),
-        }
+            _ => return None,
+        };
+        Some(product)
     }
 }
 
-impl PartialEq for SetTerminal {
+impl<S: Symbol> PartialEq for SetTerminal<S> {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (SetTerminal::SingleElement(a, a_code), SetTerminal::SingleElement(b, b_code)) => {
@@ -49,9 +61,9 @@ impl PartialEq for SetTerminal {
     }
 }
 
-impl Eq for SetTerminal {}
+impl<S: Symbol> Eq for SetTerminal<S> {}
 
-impl Hash for SetTerminal {
+impl<S: Symbol> Hash for SetTerminal<S> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         match self {
             SetTerminal::SingleElement(a, a_code) => {
@@ -92,4 +104,20 @@ mod tests {
         assert_eq!(d.product(&a), SetTerminal::Empty);
         assert_eq!(b.product(&d), SetTerminal::Empty);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_try_product() {
+        let a = SetTerminal::SingleElement('a', 1);
+        let ab = SetTerminal::DoubleElement('a', 1, 'b', 2);
+        assert_eq!(
+            a.try_product(&SetTerminal::Epsilon),
+            Some(SetTerminal::SingleElement('a', 1))
+        );
+        assert_eq!(a.try_product(&ab), None);
+        assert_eq!(ab.try_product(&SetTerminal::Epsilon), None);
+        assert_eq!(
+            ab.try_product(&SetTerminal::Empty),
+            Some(SetTerminal::Empty)
+        );
+    }
+}
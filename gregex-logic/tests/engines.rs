@@ -0,0 +1,343 @@
+//! Checks that every matching engine agrees on a set of representative patterns.
+
+use gregex_logic::dfa::DFA;
+use gregex_logic::lazy::LazyDFA;
+use gregex_logic::nfa::NFA;
+use gregex_logic::regexset::RegexSet;
+use gregex_logic::translation::node::Node;
+use gregex_logic::translation::operator::Operator;
+
+fn t(symbol: char) -> Node {
+    Node::Terminal(symbol, 0)
+}
+
+fn or(left: Node, right: Node) -> Node {
+    Node::Operation(Operator::Or, Box::new(left), Some(Box::new(right)))
+}
+
+fn dot(left: Node, right: Node) -> Node {
+    Node::Operation(Operator::Concat, Box::new(left), Some(Box::new(right)))
+}
+
+fn star(node: Node) -> Node {
+    Node::Operation(Operator::Production, Box::new(node), None)
+}
+
+/// Returns the set of offsets at which a match of `node` starting at `at` can end, by backtracking.
+fn reference_ends(node: &Node, input: &[char], at: usize) -> Vec<usize> {
+    let mut ends = match node {
+        Node::Terminal(symbol, _) => {
+            if input.get(at) == Some(symbol) {
+                vec![at + 1]
+            } else {
+                vec![]
+            }
+        }
+        Node::Epsilon => vec![at],
+        Node::Operation(Operator::Or, left, Some(right)) => {
+            let mut ends = reference_ends(left, input, at);
+            ends.extend(reference_ends(right, input, at));
+            ends
+        }
+        Node::Operation(Operator::Concat, left, Some(right)) => reference_ends(left, input, at)
+            .into_iter()
+            .flat_map(|middle| reference_ends(right, input, middle))
+            .collect(),
+        Node::Operation(Operator::Production, inner, None) => {
+            let mut ends = vec![at];
+            let mut pending = vec![at];
+            while let Some(start) = pending.pop() {
+                for end in reference_ends(inner, input, start) {
+                    if !ends.contains(&end) {
+                        ends.push(end);
+                        pending.push(end);
+                    }
+                }
+            }
+            ends
+        }
+        Node::Operation(Operator::Plus, inner, None) => {
+            let mut ends = vec![];
+            let mut pending = vec![at];
+            while let Some(start) = pending.pop() {
+                for end in reference_ends(inner, input, start) {
+                    if !ends.contains(&end) {
+                        ends.push(end);
+                        pending.push(end);
+                    }
+                }
+            }
+            ends
+        }
+        Node::Operation(Operator::Question, inner, None) => {
+            let mut ends = reference_ends(inner, input, at);
+            ends.push(at);
+            ends
+        }
+        _ => unreachable!("malformed operation"),
+    };
+    ends.sort_unstable();
+    ends.dedup();
+    ends
+}
+
+/// Asserts that the Glushkov NFA, the DFA, the DFA rebuilt from its table, lazy DFAs with a large
+/// and a tiny cache, and a single-pattern [RegexSet] all agree with a backtracking reference matcher on every input.
+fn assert_all_engines_agree(pattern: &Node, inputs: &[String]) {
+    let mut tree = pattern.clone();
+    tree.renumber();
+    let nfa = gregex_logic::regex(&tree);
+    let dfa = DFA::from_nfa(&nfa);
+    let table_dfa = DFA::from_table(&dfa.to_table());
+    let set = RegexSet::new(vec![gregex_logic::regex(&tree)]);
+    let mut lazy_dfa = LazyDFA::new(&nfa, 1000);
+    let mut small_lazy_dfa = LazyDFA::new(&nfa, 2);
+
+    for input in inputs {
+        let chars: Vec<char> = input.chars().collect();
+        let expected = reference_ends(pattern, &chars, 0).contains(&chars.len());
+        assert_eq!(
+            nfa.run(input),
+            expected,
+            "NFA on {:?} for {:?}",
+            input,
+            pattern
+        );
+        assert_eq!(
+            dfa.run(input),
+            expected,
+            "DFA on {:?} for {:?}",
+            input,
+            pattern
+        );
+        assert_eq!(
+            table_dfa.run(input),
+            expected,
+            "table DFA on {:?} for {:?}",
+            input,
+            pattern
+        );
+        assert_eq!(
+            lazy_dfa.run(input),
+            expected,
+            "lazy DFA on {:?} for {:?}",
+            input,
+            pattern
+        );
+        assert_eq!(
+            small_lazy_dfa.run(input),
+            expected,
+            "small lazy DFA on {:?} for {:?}",
+            input,
+            pattern
+        );
+        assert_eq!(
+            set.is_match(input),
+            expected,
+            "RegexSet on {:?} for {:?}",
+            input,
+            pattern
+        );
+    }
+}
+
+/// Returns every string over `alphabet` of at most `max_len` characters.
+fn all_inputs(alphabet: &[char], max_len: usize) -> Vec<String> {
+    let mut inputs = vec![String::new()];
+    let mut last = vec![String::new()];
+    for _ in 0..max_len {
+        last = last
+            .iter()
+            .flat_map(|prefix| alphabet.iter().map(move |c| format!("{}{}", prefix, c)))
+            .collect();
+        inputs.extend(last.iter().cloned());
+    }
+    inputs
+}
+
+#[test]
+fn engines_agree() {
+    let inputs = all_inputs(&['a', 'b', 'c'], 5);
+    let patterns = [
+        t('a'),
+        Node::Epsilon,
+        dot(t('a'), t('b')),
+        or(t('a'), dot(t('a'), t('b'))),
+        star(or(t('a'), t('b'))),
+        dot(star(t('a')), star(t('b'))),
+        dot(star(or(star(t('a')), t('b'))), t('c')),
+        or(
+            star(dot(t('a'), star(dot(t('a'), t('b'))))),
+            star(dot(t('b'), t('a'))),
+        ),
+        dot(or(t('a'), Node::Epsilon), or(t('b'), Node::Epsilon)),
+        star(or(or(t('a'), t('a')), dot(t('b'), t('c')))),
+        dot(dot(star(t('a')), t('b')), star(or(t('a'), t('c')))),
+    ];
+    for pattern in &patterns {
+        assert_all_engines_agree(pattern, &inputs);
+    }
+}
+
+/// Xorshift generator with a fixed seed, so the random patterns are the same on every run.
+struct Rng(u64);
+
+impl Rng {
+    /// Returns a number in `0..n`.
+    fn below(&mut self, n: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % n
+    }
+}
+
+/// Returns a random tree of at most `depth` levels over `a`, `b` and `c`, with its pattern string.
+fn random_pattern(rng: &mut Rng, depth: u32) -> (Node, String) {
+    let kind = if depth == 0 { 0 } else { rng.below(6) };
+    if kind == 0 {
+        let symbol = ['a', 'b', 'c'][rng.below(3) as usize];
+        return (t(symbol), symbol.to_string());
+    }
+    let (left, left_pattern) = random_pattern(rng, depth - 1);
+    let postfix = |op: Operator, symbol: char| {
+        (
+            Node::Operation(op, Box::new(left.clone()), None),
+            format!("({}){}", left_pattern, symbol),
+        )
+    };
+    match kind {
+        1 => postfix(Operator::Production, '*'),
+        2 => postfix(Operator::Plus, '+'),
+        3 => postfix(Operator::Question, '?'),
+        _ => {
+            let (right, right_pattern) = random_pattern(rng, depth - 1);
+            if kind == 4 {
+                let pattern = format!("({})|({})", left_pattern, right_pattern);
+                (or(left, right), pattern)
+            } else {
+                let pattern = format!("({})({})", left_pattern, right_pattern);
+                (dot(left, right), pattern)
+            }
+        }
+    }
+}
+
+/// Compares the engines, with the NFA also compiled from the pattern string, against the
+/// reference matcher on random patterns and inputs. The first divergence is reported with the
+/// pattern and the input.
+#[test]
+fn random_patterns_agree() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..300 {
+        let (tree, pattern) = random_pattern(&mut rng, 4);
+        let inputs: Vec<String> = (0..40)
+            .map(|_| {
+                let len = rng.below(8);
+                (0..len)
+                    .map(|_| ['a', 'b', 'c'][rng.below(3) as usize])
+                    .collect()
+            })
+            .collect();
+        assert_all_engines_agree(&tree, &inputs);
+
+        let parsed = NFA::new(&pattern).unwrap();
+        let oracle = regex::Regex::new(&format!("^(?:{})$", pattern)).unwrap();
+        for input in &inputs {
+            let chars: Vec<char> = input.chars().collect();
+            let expected = reference_ends(&tree, &chars, 0).contains(&chars.len());
+            assert_eq!(
+                parsed.run(input),
+                expected,
+                "pattern {:?} on {:?}",
+                pattern,
+                input
+            );
+            assert_eq!(
+                oracle.is_match(input),
+                expected,
+                "regex crate on pattern {:?} and {:?}",
+                pattern,
+                input
+            );
+        }
+    }
+}
+
+/// Returns a random pattern string of at most `depth` levels also using classes, counted
+/// repetitions, escapes and case-insensitive groups, written in the syntax shared with the `regex` crate.
+fn random_syntax(rng: &mut Rng, depth: u32) -> String {
+    let kind = if depth == 0 {
+        rng.below(3)
+    } else {
+        rng.below(9)
+    };
+    match kind {
+        0 => ["a", "b", "C", r"\*"][rng.below(4) as usize].to_string(),
+        1 => ["[ab]", "[a-cX]", "[-b]", r"[\]a]"][rng.below(4) as usize].to_string(),
+        2 => "()".to_string(),
+        3 => format!("({})*", random_syntax(rng, depth - 1)),
+        4 => format!("({})+", random_syntax(rng, depth - 1)),
+        5 => {
+            let min = rng.below(3);
+            let max = min + rng.below(3);
+            let bounds = match rng.below(3) {
+                0 => format!("{{{}}}", min),
+                1 => format!("{{{},}}", min),
+                _ => format!("{{{},{}}}", min, max),
+            };
+            format!("({}){}", random_syntax(rng, depth - 1), bounds)
+        }
+        6 => format!("(?i:{})", random_syntax(rng, depth - 1)),
+        7 => format!(
+            "({})|({})",
+            random_syntax(rng, depth - 1),
+            random_syntax(rng, depth - 1)
+        ),
+        _ => format!(
+            "({})({})?",
+            random_syntax(rng, depth - 1),
+            random_syntax(rng, depth - 1)
+        ),
+    }
+}
+
+/// Compares the NFA compiled from random pattern strings, the DFA and the leftmost search with
+/// the `regex` crate.
+#[test]
+fn regex_crate_agrees() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    let alphabet = ['a', 'b', 'c', 'A', 'B', 'C', 'X', '*', ']', '-'];
+    for _ in 0..300 {
+        let pattern = random_syntax(&mut rng, 4);
+        let nfa = NFA::new(&pattern).unwrap();
+        let dfa = DFA::from_nfa(&nfa);
+        let anchored = regex::Regex::new(&format!("^(?:{})$", pattern)).unwrap();
+        let oracle = regex::Regex::new(&pattern).unwrap();
+        for _ in 0..40 {
+            let len = rng.below(8);
+            let input: String = (0..len)
+                .map(|_| alphabet[rng.below(alphabet.len() as u64) as usize])
+                .collect();
+            let expected = anchored.is_match(&input);
+            assert_eq!(
+                nfa.run(&input),
+                expected,
+                "NFA on {:?} and {:?}",
+                pattern,
+                input
+            );
+            assert_eq!(
+                dfa.run(&input),
+                expected,
+                "DFA on {:?} and {:?}",
+                pattern,
+                input
+            );
+            // Both searches start at the leftmost position where a match starts
+            let found = nfa.leftmost_find(&input).map(|m| m.start());
+            let start = oracle.find(&input).map(|m| m.start());
+            assert_eq!(found, start, "search on {:?} and {:?}", pattern, input);
+        }
+    }
+}
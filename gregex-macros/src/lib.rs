@@ -6,29 +6,40 @@ use proc_macro::TokenStream;
 use quote::quote;
 use syn::{parse_macro_input, Expr, ExprLit, ExprMacro, Lit};
 
+/// Converts an operand of the macros to the tokens building its node.
+///
+/// A char literal is a terminal, a string literal the concatenation of its characters and a
+/// nested macro is expanded as is. The codes only need to be unique once `regex!` renumbers the tree.
+fn operand(expr: &Expr) -> proc_macro2::TokenStream {
+    match expr {
+        Expr::Macro(ExprMacro { mac, .. }) => {
+            // Handle procedural macro
+            quote! { #mac }
+        }
+        Expr::Lit(ExprLit { lit, .. }) => match lit {
+            Lit::Char(c) => {
+                let count =
+                    gregex_logic::TERMINAL_COUNT.fetch_add(1, core::sync::atomic::Ordering::SeqCst);
+                quote! {
+                    gregex_logic::translation::node::Node::Terminal(#c, #count)
+                }
+            }
+            Lit::Str(s) => {
+                quote! {
+                    gregex_logic::translation::node::Node::from_str_literal(#s)
+                }
+            }
+            _ => panic!("Unsupported literal type"),
+        },
+        _ => panic!("Unsupported input type"),
+    }
+}
+
 #[proc_macro]
 pub fn dot(input: TokenStream) -> TokenStream {
     let inputs = parse_macro_input!(input with syn::punctuated::Punctuated::<Expr, syn::Token![,]>::parse_terminated);
 
-    let nodes = inputs.iter().map(|expr| {
-        match expr {
-            Expr::Macro(ExprMacro { mac, .. }) => {
-                // Handle procedural macro
-                quote! { #mac }
-            }
-            Expr::Lit(ExprLit { lit, .. }) => match lit {
-                Lit::Char(c) => {
-                    let count = gregex_logic::TERMINAL_COUNT
-                        .fetch_add(1, core::sync::atomic::Ordering::SeqCst);
-                    quote! {
-                        gregex_logic::translation::node::Node::Terminal(#c, #count)
-                    }
-                }
-                _ => panic!("Unsupported literal type"),
-            },
-            _ => panic!("Unsupported input type"),
-        }
-    });
+    let nodes = inputs.iter().map(operand);
 
     // Generate the code for concatenating nodes
     let mut iter = nodes.into_iter();
@@ -55,25 +66,7 @@ pub fn dot(input: TokenStream) -> TokenStream {
 pub fn or(input: TokenStream) -> TokenStream {
     let inputs = parse_macro_input!(input with syn::punctuated::Punctuated::<Expr, syn::Token![,]>::parse_terminated);
 
-    let nodes = inputs.iter().map(|expr| {
-        match expr {
-            Expr::Macro(ExprMacro { mac, .. }) => {
-                // Handle procedural macro
-                quote! { #mac }
-            }
-            Expr::Lit(ExprLit { lit, .. }) => match lit {
-                Lit::Char(c) => {
-                    let count = gregex_logic::TERMINAL_COUNT
-                        .fetch_add(1, core::sync::atomic::Ordering::SeqCst);
-                    quote! {
-                        gregex_logic::translation::node::Node::Terminal(#c, #count)
-                    }
-                }
-                _ => panic!("Unsupported literal type"),
-            },
-            _ => panic!("Unsupported input type"),
-        }
-    });
+    let nodes = inputs.iter().map(operand);
 
     // Generate the code for concatenating nodes
     let mut iter = nodes.into_iter();
@@ -100,23 +93,7 @@ pub fn or(input: TokenStream) -> TokenStream {
 pub fn star(input: TokenStream) -> TokenStream {
     let expr = parse_macro_input!(input as Expr);
 
-    let node = match expr {
-        Expr::Macro(ExprMacro { mac, .. }) => {
-            // Handle procedural macro
-            quote! { #mac }
-        }
-        Expr::Lit(ExprLit { lit, .. }) => match lit {
-            Lit::Char(c) => {
-                let count =
-                    gregex_logic::TERMINAL_COUNT.fetch_add(1, core::sync::atomic::Ordering::SeqCst);
-                quote! {
-                    gregex_logic::translation::node::Node::Terminal(#c, #count)
-                }
-            }
-            _ => panic!("Unsupported literal type"),
-        },
-        _ => panic!("Unsupported input type"),
-    };
+    let node = operand(&expr);
 
     // Generate the code for the star operation
     let operation = quote! {
@@ -140,34 +117,58 @@ pub fn regex(input: TokenStream) -> TokenStream {
     let expr = parse_macro_input!(input as Expr);
 
     // Convert the input expression into a Node structure
-    let node = match expr {
-        Expr::Macro(ExprMacro { mac, .. }) => {
-            // Handle procedural macro
-            quote! { #mac }
-        }
-        Expr::Lit(ExprLit { lit, .. }) => match lit {
-            Lit::Char(c) => {
-                let count =
-                    gregex_logic::TERMINAL_COUNT.fetch_add(1, core::sync::atomic::Ordering::SeqCst);
-                quote! {
-                    gregex_logic::translation::node::Node::Terminal(#c, #count)
-                }
-            }
-            _ => panic!("Unsupported literal type"),
-        },
-        _ => panic!("Unsupported input type"),
-    };
+    let node = operand(&expr);
 
     // Generate the code to convert the Node into a Regex
     let gen = quote! {
         {
-            let regex_tree = #node;
-            let prefix_set = gregex_logic::translation::node::prefix_set(&regex_tree);
-            let suffix_set = gregex_logic::translation::node::suffix_set(&regex_tree);
-            let factors_set = gregex_logic::translation::node::factors_set(&regex_tree);
-            gregex_logic::nfa::NFA::set_to_nfa(&prefix_set, &suffix_set, &factors_set)
+            let mut regex_tree = #node;
+            regex_tree.renumber();
+            gregex_logic::regex(&regex_tree)
         }
     };
 
     gen.into()
 }
+
+#[proc_macro]
+pub fn repeat(input: TokenStream) -> TokenStream {
+    let inputs = parse_macro_input!(input with syn::punctuated::Punctuated::<Expr, syn::Token![,]>::parse_terminated);
+
+    let mut iter = inputs.iter();
+    let symbol = match iter.next() {
+        Some(Expr::Lit(ExprLit {
+            lit: Lit::Char(c), ..
+        })) => c,
+        _ => panic!("Expected a char literal to repeat"),
+    };
+    let count = match iter.next() {
+        Some(Expr::Lit(ExprLit {
+            lit: Lit::Int(n), ..
+        })) => n
+            .base10_parse::<u32>()
+            .expect("The count must fit in a u32"),
+        _ => panic!("Expected the repetition count"),
+    };
+    // The concatenation chain is built at runtime, so long repetitions do not bloat the generated code
+    let gen = quote! {
+        gregex_logic::translation::node::Node::repeat(#symbol, #count)
+    };
+
+    gen.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operand_test() {
+        let tokens = operand(&syn::parse_str("\"ab\"").unwrap()).to_string();
+        assert!(tokens.contains("from_str_literal (\"ab\")"), "{}", tokens);
+        let tokens = operand(&syn::parse_str("'a'").unwrap()).to_string();
+        assert!(tokens.contains("Node :: Terminal ('a'"), "{}", tokens);
+        let tokens = operand(&syn::parse_str("star!(\"ab\")").unwrap()).to_string();
+        assert_eq!(tokens, "star ! (\"ab\")");
+    }
+}
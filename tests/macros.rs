@@ -0,0 +1,113 @@
+use gregex::*;
+
+#[test]
+fn two_regexes_in_one_file() {
+    let first = regex!(dot!('a', star!('b')));
+    let second = regex!(dot!('b', 'a'));
+
+    assert!(first.run("a"));
+    assert!(first.run("abbb"));
+    assert!(!first.run("ba"));
+
+    assert!(second.run("ba"));
+    assert!(!second.run("a"));
+    assert!(!second.run("abbb"));
+}
+
+#[test]
+fn star_matches_empty_input() {
+    let runner = regex!(star!('a'));
+    assert!(runner.run(""));
+    assert!(runner.run("aaa"));
+    assert!(!runner.run("b"));
+}
+
+#[test]
+fn long_repetition() {
+    let runner = regex!(repeat!('a', 50_000));
+    assert!(runner.run("a".repeat(50_000)));
+    assert!(!runner.run("a".repeat(49_999)));
+}
+
+#[test]
+fn repeat_zero_times() {
+    let runner = regex!(dot!('b', repeat!('a', 0)));
+    assert!(runner.run("b"));
+    assert!(!runner.run("ba"));
+}
+
+#[test]
+fn string_literals() {
+    let runner = regex!(dot!("ab", star!("cd"), 'e'));
+    assert!(runner.run("abe"));
+    assert!(runner.run("abcdcde"));
+    assert!(!runner.run("abce"));
+    assert!(!runner.run("ab"));
+}
+
+#[test]
+fn nested_macros() {
+    let runner = regex!(star!(dot!("ab")));
+    assert!(runner.run(""));
+    assert!(runner.run("abab"));
+    assert!(!runner.run("aba"));
+
+    let runner = regex!(or!(star!(or!('a', "bc")), dot!('a', "")));
+    assert!(runner.run("abca"));
+    assert!(runner.run("a"));
+    assert!(!runner.run("b"));
+}
+
+#[test]
+fn parsed_tree_equals_macro_tree() {
+    use gregex_logic::translation::node::Node;
+
+    let mut from_macros = or!(dot!('a', star!('b')), 'c');
+    from_macros.renumber();
+    let parsed: Node = "ab*|c".try_into().unwrap();
+    assert_eq!(parsed, from_macros);
+}
+
+#[test]
+fn stats() {
+    use gregex_logic::nfa::RegexStats;
+
+    let runner = regex!(star!(or!('a', 'b')));
+    assert_eq!(
+        runner.stats(),
+        RegexStats {
+            states: 3,
+            transitions: 6,
+            accept_states: 3,
+            nullable: true,
+            alphabet_size: 2,
+            max_depth: 3,
+        }
+    );
+}
+
+#[test]
+fn as_literal() {
+    assert_eq!(
+        regex!(dot!('a', 'b', 'c')).as_literal(),
+        Some("abc".to_string())
+    );
+    assert_eq!(regex!(star!('a')).as_literal(), None);
+}
+
+#[test]
+fn operator_counts() {
+    use gregex_logic::translation::operator::Operator;
+    use std::collections::HashMap;
+
+    let tree = star!(or!('a', dot!('b', 'c')));
+    assert_eq!(
+        tree.operator_counts(),
+        HashMap::from([
+            (Operator::Production, 1),
+            (Operator::Or, 1),
+            (Operator::Concat, 1),
+        ])
+    );
+    assert!(dot!('a').operator_counts().is_empty());
+}